@@ -0,0 +1,340 @@
+//! This module deals with the collection of information from archive files.
+//!
+//! Entries are read straight from the archive, nothing is extracted to disk. Paths inside the
+//! archive are matched against the walk options as if the archive were a directory, including the
+//! `.oneloignore` files found in it.
+
+use crate::context::Result;
+use crate::filesystem::{self, IgnoreFiles, Stamp, WalkOptions, IGNORE_FILENAME};
+use chrono::prelude::*;
+use flate2::read::GzDecoder;
+use globset::GlobSet;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path};
+
+/// The supported archive formats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl Format {
+    /// Guesses the format of an archive from its file name: `.tar`, `.tar.gz`, `.tgz` or `.zip`.
+    pub fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+
+        if name.ends_with(".tar") {
+            Some(Format::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Format::TarGz)
+        } else if name.ends_with(".zip") {
+            Some(Format::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// A file found in an archive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+    /// The path inside the archive using `/` as separator.
+    pub path: String,
+    pub stamp: Stamp,
+    /// The content, left unread when the file was deemed fresh.
+    pub content: Option<Vec<u8>>,
+}
+
+/// Get all files from an archive matching the given options, sorted by path.
+///
+/// The content of the files for which `fresh` holds given their path and stamp is not read.
+pub fn get_files<F>(
+    route: &Path,
+    format: Format,
+    options: &WalkOptions,
+    fresh: F,
+) -> Result<Vec<File>>
+where
+    F: Fn(&str, &Stamp) -> bool,
+{
+    let mut walk = Walk {
+        options,
+        fresh: &fresh,
+        include: filesystem::glob_set(&options.include)?,
+        exclude: filesystem::glob_set(&options.exclude)?,
+        ignores: IgnoreFiles::default(),
+        files: Vec::new(),
+    };
+    let file = fs::File::open(route).map_err(|err| format!("{}: {}", route.display(), err))?;
+
+    match format {
+        Format::Tar => walk.tar(file),
+        Format::TarGz => walk.tar(GzDecoder::new(file)),
+        Format::Zip => walk.zip(file),
+    }
+    .map_err(|err| format!("{}: {}", route.display(), err))?;
+
+    let Walk {
+        ignores, mut files, ..
+    } = walk;
+
+    files.retain(|file| !ignores.is_ignored(&file.path));
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(files)
+}
+
+/// The state of a walk through the entries of an archive.
+struct Walk<'a> {
+    options: &'a WalkOptions,
+    fresh: &'a dyn Fn(&str, &Stamp) -> bool,
+    include: GlobSet,
+    exclude: GlobSet,
+    ignores: IgnoreFiles,
+    files: Vec<File>,
+}
+
+impl<'a> Walk<'a> {
+    fn tar<R: Read>(&mut self, reader: R) -> Result<()> {
+        let mut archive = tar::Archive::new(reader);
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let path = match normalise(&entry.path()?) {
+                Some(path) => path,
+                None => continue,
+            };
+            let stamp = Stamp {
+                size: entry.header().size()?,
+                modified: Utc.timestamp(entry.header().mtime()? as i64, 0),
+                digest: None,
+            };
+
+            self.visit(path, stamp, |content| entry.read_to_end(content))?;
+        }
+
+        Ok(())
+    }
+
+    fn zip(&mut self, file: fs::File) -> Result<()> {
+        let mut archive = zip::ZipArchive::new(file)?;
+
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+
+            if !entry.is_file() {
+                continue;
+            }
+
+            let path = match entry.enclosed_name().and_then(normalise) {
+                Some(path) => path,
+                None => continue,
+            };
+            let modified = entry.last_modified();
+            let stamp = Stamp {
+                size: entry.size(),
+                modified: Utc
+                    .ymd_opt(
+                        modified.year().into(),
+                        modified.month().into(),
+                        modified.day().into(),
+                    )
+                    .and_hms_opt(
+                        modified.hour().into(),
+                        modified.minute().into(),
+                        modified.second().into(),
+                    )
+                    .single()
+                    .unwrap_or_else(|| Utc.timestamp(0, 0)),
+                digest: None,
+            };
+
+            self.visit(path, stamp, |content| entry.read_to_end(content))?;
+        }
+
+        Ok(())
+    }
+
+    /// Keeps the entry at the given path if it matches the walk options, reading its content unless
+    /// it is fresh.
+    fn visit<F>(&mut self, path: String, stamp: Stamp, read: F) -> Result<()>
+    where
+        F: FnOnce(&mut Vec<u8>) -> std::io::Result<usize>,
+    {
+        let (dir, name) = match path.rfind('/') {
+            Some(i) => (&path[..=i], &path[i + 1..]),
+            None => ("", path.as_str()),
+        };
+
+        if (!self.options.recursive && !dir.is_empty()) || self.is_excluded(&path) {
+            return Ok(());
+        }
+
+        if self.options.ignore && name == IGNORE_FILENAME {
+            let mut content = Vec::new();
+            read(&mut content)?;
+
+            return self.ignores.add(dir, &content);
+        }
+
+        if self.include.is_match(&path) {
+            let content = if (self.fresh)(&path, &stamp) {
+                None
+            } else {
+                let mut content = Vec::new();
+                read(&mut content)?;
+                Some(content)
+            };
+
+            self.files.push(File {
+                path,
+                stamp,
+                content,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Whether the path or any of its directories matches an exclude glob.
+    fn is_excluded(&self, path: &str) -> bool {
+        path.match_indices('/')
+            .map(|(i, _)| &path[..i])
+            .chain(std::iter::once(path))
+            .any(|prefix| self.exclude.is_match(prefix))
+    }
+}
+
+/// Turns a path inside an archive into a relative path with `/` as separator, dropping any `./`
+/// and refusing to leave the archive root.
+fn normalise(path: &Path) -> Option<String> {
+    let mut parts = Vec::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const FILES: [(&str, &str); 5] = [
+        ("./a.md", "# A"),
+        ("notes.txt", "Not Markdown"),
+        ("sub/b.md", "# B"),
+        ("sub/.oneloignore", "draft.md\n"),
+        ("sub/draft.md", "# Draft"),
+    ];
+
+    fn tar<W: Write>(writer: W) -> Result<W> {
+        let mut builder = tar::Builder::new(writer);
+
+        for (path, content) in &FILES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mtime(1000);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, content.as_bytes())?;
+        }
+
+        Ok(builder.into_inner()?)
+    }
+
+    fn paths(files: &[File]) -> Vec<&str> {
+        files.iter().map(|file| file.path.as_str()).collect()
+    }
+
+    #[test]
+    fn guess_format() {
+        assert_eq!(Format::of(Path::new("a/b.tar")), Some(Format::Tar));
+        assert_eq!(Format::of(Path::new("b.TAR.GZ")), Some(Format::TarGz));
+        assert_eq!(Format::of(Path::new("b.tgz")), Some(Format::TarGz));
+        assert_eq!(Format::of(Path::new("b.zip")), Some(Format::Zip));
+        assert_eq!(Format::of(Path::new("b.md")), None);
+    }
+
+    #[test]
+    fn read_archives() -> Result<()> {
+        let dir = TempDir::new("archive")?;
+
+        fs::write(dir.join("notes.tar"), tar(Vec::new())?)?;
+        fs::write(
+            dir.join("notes.tar.gz"),
+            tar(GzEncoder::new(Vec::new(), Compression::default()))?.finish()?,
+        )?;
+
+        let mut zip = zip::ZipWriter::new(fs::File::create(dir.join("notes.zip"))?);
+
+        for (path, content) in &FILES {
+            zip.start_file(path.trim_start_matches("./"), Default::default())?;
+            zip.write_all(content.as_bytes())?;
+        }
+
+        zip.finish()?;
+
+        let options = WalkOptions::default();
+        let stale = |_: &str, _: &Stamp| false;
+        let tar = get_files(&dir.join("notes.tar"), Format::Tar, &options, stale)?;
+        let tar_gz = get_files(&dir.join("notes.tar.gz"), Format::TarGz, &options, stale)?;
+        let zip = get_files(&dir.join("notes.zip"), Format::Zip, &options, stale)?;
+        let fresh = get_files(&dir.join("notes.zip"), Format::Zip, &options, |path, _| {
+            path == "a.md"
+        })?;
+        let excluded = get_files(
+            &dir.join("notes.zip"),
+            Format::Zip,
+            &WalkOptions {
+                exclude: vec!["sub/".into()],
+                ignore: false,
+                ..WalkOptions::default()
+            },
+            stale,
+        )?;
+        let unignored = get_files(
+            &dir.join("notes.tar"),
+            Format::Tar,
+            &WalkOptions {
+                ignore: false,
+                ..WalkOptions::default()
+            },
+            stale,
+        )?;
+
+        assert_eq!(paths(&tar), vec!["a.md", "sub/b.md"]);
+        assert_eq!(tar[0].content.as_deref(), Some(&b"# A"[..]));
+        assert_eq!(tar[0].stamp.modified, Utc.timestamp(1000, 0));
+        assert_eq!(tar_gz, tar);
+        assert_eq!(paths(&zip), vec!["a.md", "sub/b.md"]);
+        assert_eq!(zip[1].content.as_deref(), Some(&b"# B"[..]));
+        assert_eq!(fresh[0].content, None);
+        assert_eq!(fresh[1].content, zip[1].content);
+        assert_eq!(paths(&excluded), vec!["a.md"]);
+        assert_eq!(paths(&unignored), vec!["a.md", "sub/b.md", "sub/draft.md"]);
+
+        Ok(())
+    }
+}
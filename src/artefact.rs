@@ -26,4 +26,15 @@ impl Content {
     pub fn checksum(&self) -> &Checksum {
         &self.id
     }
+
+    pub fn blob(&self) -> &Blob {
+        &self.blob
+    }
+}
+
+impl From<Blob> for Content {
+    /// Hashes the blob to identify it.
+    fn from(blob: Blob) -> Self {
+        Content::new(Checksum::new(&blob), blob)
+    }
 }
@@ -0,0 +1,214 @@
+//! This module is concerned with finding the nodes linking to a given node.
+//!
+//! Every backlink carries the sentence surrounding the connection so it can be read without
+//! opening the origin entry.
+
+use crate::cache;
+use crate::check::position;
+use crate::context::Result;
+use crate::front_matter;
+use crate::source_entry::SourceEntry;
+use rusqlite::Connection;
+use std::fmt;
+use std::ops::Range;
+
+/// A connection from another node to the queried one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backlink {
+    pub origin_id: String,
+    pub source_id: String,
+    pub path: String,
+    pub title: String,
+    pub line: usize,
+    pub column: usize,
+    /// The sentence the connection was found in.
+    pub context: String,
+}
+
+impl fmt::Display for Backlink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}: {}",
+            self.source_id, self.path, self.line, self.column, self.context
+        )
+    }
+}
+
+/// Finds every connection resolved to the node extracted from the given entry.
+pub fn find(conn: &Connection, entry: &SourceEntry) -> Result<Vec<Backlink>> {
+    let target_id = cache::get_node_id(conn, entry.source_id().as_str(), entry.path())?
+        .ok_or_else(|| format!("Unknown entry `{}`", entry.id()))?;
+    let mut content: Option<(String, String)> = None;
+    let mut backlinks = Vec::new();
+
+    for incoming in cache::get_incoming_connections(conn, &target_id)? {
+        let text = match content {
+            Some((ref id, ref text)) if id == &incoming.origin_id => text,
+            _ => {
+                let blob = cache::get_entry_content(conn, &incoming.origin_id)?;
+                let text = String::from_utf8(blob)?;
+
+                &content.insert((incoming.origin_id.clone(), text)).1
+            }
+        };
+        let (line, column) = position(text, incoming.span.start);
+
+        backlinks.push(Backlink {
+            context: sentence(text, &incoming.span),
+            origin_id: incoming.origin_id,
+            source_id: incoming.source_id,
+            path: incoming.path,
+            title: incoming.title,
+            line,
+            column,
+        });
+    }
+
+    Ok(backlinks)
+}
+
+/// Extracts the sentence surrounding the given span, collapsing whitespace.
+///
+/// Sentences end with `.`, `!` or `?` followed by whitespace and never cross a Markdown block
+/// such as a paragraph, a heading or a list item.
+pub fn sentence(content: &str, span: &Range<usize>) -> String {
+    let body_start = front_matter::split(content)
+        .map(|split| split.body_offset)
+        .unwrap_or(0)
+        .min(span.start);
+    let block = block(content, body_start, span);
+    let bytes = content.as_bytes();
+
+    let mut start = block.start;
+    for i in (block.start..span.start).rev() {
+        if is_terminator(bytes, i, block.end) {
+            start = i + 1;
+            break;
+        }
+    }
+
+    let mut end = block.end;
+    for i in span.end..block.end {
+        if is_terminator(bytes, i, block.end) {
+            end = i + 1;
+            break;
+        }
+    }
+
+    content[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Finds the byte range of the Markdown block containing the span, without its block marker.
+fn block(content: &str, body_start: usize, span: &Range<usize>) -> Range<usize> {
+    let mut start = body_start;
+    let mut end = content.len();
+    let mut offset = body_start;
+
+    for line in content[body_start..].split_inclusive('\n') {
+        let line_end = offset + line.len();
+
+        if offset <= span.start {
+            if line.trim().is_empty() {
+                start = line_end;
+            } else if let Some(marker) = block_marker(line) {
+                start = offset + marker;
+            }
+        } else if offset >= span.end && (line.trim().is_empty() || block_marker(line).is_some()) {
+            end = offset;
+            break;
+        }
+
+        offset = line_end;
+    }
+
+    start..end
+}
+
+/// Returns the length of the marker when the line starts a heading, a list item or a quote.
+fn block_marker(line: &str) -> Option<usize> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let marker = if rest.starts_with('#') {
+        rest.bytes().take_while(|&b| b == b'#').count()
+    } else if rest.starts_with(['-', '*', '+', '>']) {
+        1
+    } else if digits > 0 && rest[digits..].starts_with(['.', ')']) {
+        digits + 1
+    } else {
+        return None;
+    };
+
+    if rest[marker..].starts_with(char::is_whitespace) {
+        Some(indent + marker)
+    } else {
+        None
+    }
+}
+
+fn is_terminator(bytes: &[u8], i: usize, end: usize) -> bool {
+    matches!(bytes[i], b'.' | b'!' | b'?') && (i + 1 >= end || bytes[i + 1].is_ascii_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+    use crate::source::Source;
+    use crate::{extraction, resolution, sourcing};
+    use std::fs;
+
+    fn context_of(content: &str, link: &str) -> String {
+        let start = content.find(link).unwrap();
+
+        sentence(content, &(start..start + link.len()))
+    }
+
+    #[test]
+    fn sentence_context() {
+        let content = "+++\ntitle = \"A\"\n+++\n# Alpha\n\nFirst. Then [b](b.md) is\nlinked! Last.\n\n- one [[b]] item\n- two\n";
+
+        assert_eq!(
+            context_of(content, "[b](b.md)"),
+            "Then [b](b.md) is linked!"
+        );
+        assert_eq!(context_of(content, "[[b]]"), "one [[b]] item");
+        assert_eq!(context_of("[[b]] alone", "[[b]]"), "[[b]] alone");
+    }
+
+    #[test]
+    fn find_backlinks() -> Result<()> {
+        let dir = TempDir::new("backlinks")?;
+        fs::write(dir.join("a.md"), "# A\n\nSee [b](b.md). Or not.\n")?;
+        fs::write(dir.join("b.md"), "# B\n\nNothing here.\n")?;
+        fs::write(dir.join("c.md"), "Both [[B]] and [[a]].\n")?;
+
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        let source = Source::new("test".parse()?, &dir);
+
+        sourcing::run(&conn, &source)?;
+        extraction::run(&conn, &Default::default())?;
+        resolution::run(&conn)?;
+        let actual: Vec<String> = find(&conn, &"test:b.md".parse()?)?
+            .iter()
+            .map(|backlink| backlink.to_string())
+            .collect();
+        let unknown = find(&conn, &"test:d.md".parse()?);
+
+        assert_eq!(
+            actual,
+            vec![
+                "test:a.md:3:5: See [b](b.md).",
+                "test:c.md:1:6: Both [[B]] and [[a]]."
+            ]
+        );
+        assert!(unknown.is_err());
+
+        Ok(())
+    }
+}
@@ -2,10 +2,23 @@
 //!
 //! This module contains the cache implementation and helper functions.
 
-use super::context::Result;
-use rusqlite::Connection;
+use super::artefact::{Blob, Content};
+use super::checksum::Checksum;
+use super::connection::{Connection as NodeConnection, ConnectionType};
+use super::content_type::ContentType;
+use super::context::{Context, Result};
+use super::filesystem::Stamp;
+use super::front_matter::{self, Document, Metadata};
+use super::graph::{Edge, GraphNode};
+use super::node::Node;
+use super::source::{Id as SourceId, Source};
+use super::source_entry::{self, History, SourceEntry};
+use chrono::prelude::*;
+use rusqlite::{params, Connection, OpenFlags};
+use std::collections::HashMap;
 use std::include_str;
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
 /// Opens a SQLite database at the given path.
 pub fn connect<P: AsRef<Path>>(path: P) -> Result<Connection> {
@@ -16,6 +29,31 @@ pub fn connect<P: AsRef<Path>>(path: P) -> Result<Connection> {
     Ok(conn)
 }
 
+/// Opens the cache left by a previous build, failing with a hint to build first when there is
+/// none or when it was built with another schema version.
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Connection> {
+    let path = path.as_ref();
+    let hint = |problem: &str| {
+        format!(
+            "{} cache at {}, run `onelo build` first",
+            problem,
+            path.display()
+        )
+    };
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|_| hint("No"))?;
+    let version: i64 = conn.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+
+    if version != schema_version() {
+        return Err(hint("Outdated").into());
+    }
+
+    Ok(conn)
+}
+
 /// WAL persists across connections, this ensures it is switched off.
 pub fn clean(conn: &Connection) -> Result<()> {
     conn.pragma_update(None, "wal_checkpoint", &"restart")?;
@@ -24,20 +62,891 @@ pub fn clean(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// The statements creating the cache schema.
+const BOOTSTRAP: &str = include_str!("./sql/bootstrap.sql");
+
+/// The version of the cache schema, stored as the database `user_version`.
+///
+/// It is derived from the schema itself so any change to the tables empties the caches built
+/// before it instead of leaving them with missing columns.
+fn schema_version() -> i64 {
+    let checksum = Checksum::new(BOOTSTRAP.as_bytes());
+    let bytes = checksum.as_bytes();
+
+    i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).into()
+}
+
 /// Sets up the cache schema.
+///
+/// A cache with a different schema version is emptied first given that everything in it can be
+/// sourced again.
 pub fn bootstrap(conn: &Connection) -> Result<()> {
-    let bootstrap = include_str!("./sql/bootstrap.sql");
+    let version: i64 = conn.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+    let schema_version = schema_version();
+
+    if version != schema_version {
+        let mut stmt = conn.prepare("SELECT name FROM sqlite_schema WHERE type = 'table'")?;
+        let tables = stmt
+            .query_map(params![], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+
+        conn.pragma_update(None, "foreign_keys", &false)?;
+
+        for table in tables {
+            conn.execute_batch(&format!("DROP TABLE \"{}\"", table))?;
+        }
+
+        conn.pragma_update(None, "foreign_keys", &true)?;
+        conn.pragma_update(None, "user_version", &schema_version)?;
+    }
+
+    conn.execute_batch(BOOTSTRAP)?;
+
+    Ok(())
+}
+
+/// Stores the contextual information of the current process.
+pub fn insert_context(conn: &Connection, context: &Context) -> Result<()> {
+    let mut stmt = conn.prepare("INSERT OR REPLACE INTO context (key, value) VALUES (?1, ?2)")?;
+
+    stmt.execute(params!["checksum", context.checksum()])?;
+    stmt.execute(params!["version", context.version()])?;
+    stmt.execute(params!["created", context.created().to_rfc3339()])?;
+
+    Ok(())
+}
+
+/// Fetches a contextual value, if any.
+pub fn get_context_value(conn: &Connection, key: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare("SELECT value FROM context WHERE key = ?1")?;
+    let mut rows = stmt.query(params![key])?;
+
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// Stores a contextual value, replacing any previous one.
+pub fn insert_context_value(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO context (key, value) VALUES (?1, ?2)",
+        params![key, value],
+    )?;
+
+    Ok(())
+}
+
+/// Stores a source, replacing any previous record with the same identifier.
+pub fn insert_source(conn: &Connection, source: &Source) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO source (id, route, checksum, timestamp) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            source.id().as_str(),
+            source.route().to_string_lossy(),
+            source.checksum().map(hex::encode),
+            source.timestamp().to_rfc3339(),
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Stores a content type unless it is already known.
+pub fn insert_content_type(conn: &Connection, content_type: &ContentType) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO content_type (id) VALUES (?1)",
+        params![content_type.as_iana()],
+    )?;
+
+    Ok(())
+}
+
+/// Stores a piece of content unless it is already known.
+///
+/// Content is addressed by its checksum so the same blob found in different entries is stored
+/// once.
+pub fn insert_content(conn: &Connection, content: &Content) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO content (id, content) VALUES (?1, ?2)",
+        params![content.checksum().to_string(), content.blob()],
+    )?;
+
+    Ok(())
+}
+
+/// Stores a source entry, replacing any previous record with the same identifier.
+pub fn insert_source_entry(conn: &Connection, entry: &SourceEntry, stamp: &Stamp) -> Result<()> {
+    conn.execute(
+        r#"
+        INSERT OR REPLACE INTO source_entry
+            (id, source_id, path, content_id, content_type_id, size, modified, digest)
+        VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        "#,
+        params![
+            entry.id(),
+            entry.source_id().as_str(),
+            entry.path(),
+            entry.content_id().map(|id| id.to_string()),
+            entry.content_type().as_iana(),
+            stamp.size as i64,
+            format_stamp_time(&stamp.modified),
+            stamp.digest,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// A source entry as previously recorded in the cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedEntry {
+    pub content_id: String,
+    pub stamp: Stamp,
+}
+
+/// Fetches the entries recorded for the given source keyed by entry path.
+pub fn get_source_entries(
+    conn: &Connection,
+    source: &Source,
+) -> Result<HashMap<String, CachedEntry>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            path, content_id, size, modified, digest
+        FROM
+            source_entry
+        WHERE
+            source_id = ?1
+        "#,
+    )?;
+    let rows = stmt.query_map(params![source.id().as_str()], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, Option<String>>(4)?,
+        ))
+    })?;
+    let mut entries = HashMap::new();
+
+    for row in rows {
+        let (path, content_id, size, modified, digest) = row?;
+        let stamp = Stamp {
+            size: size as u64,
+            modified: DateTime::parse_from_rfc3339(&modified)?.with_timezone(&Utc),
+            digest,
+        };
+
+        entries.insert(path, CachedEntry { content_id, stamp });
+    }
+
+    Ok(entries)
+}
+
+/// Removes a source entry along with its front matter, its node and the node connections.
+pub fn delete_source_entry(conn: &Connection, source_id: &SourceId, path: &str) -> Result<()> {
+    let id = source_entry::id(source_id, path);
+    let id = id.as_str();
+
+    delete_connections(conn, id)?;
+    conn.execute(
+        "UPDATE connection SET target_id = NULL WHERE target_id = ?1",
+        params![id],
+    )?;
+    conn.execute("DELETE FROM node WHERE id = ?1", params![id])?;
+    conn.execute(
+        "DELETE FROM front_matter WHERE source_entry_id = ?1",
+        params![id],
+    )?;
+    conn.execute(
+        "DELETE FROM history WHERE source_entry_id = ?1",
+        params![id],
+    )?;
+    conn.execute("DELETE FROM source_entry WHERE id = ?1", params![id])?;
+
+    Ok(())
+}
+
+/// Fetches the checksum recorded for a source, if any.
+pub fn get_source_checksum(conn: &Connection, source_id: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare("SELECT checksum FROM source WHERE id = ?1")?;
+    let mut rows = stmt.query(params![source_id])?;
+
+    match rows.next()? {
+        Some(row) => Ok(row.get(0)?),
+        None => Ok(None),
+    }
+}
+
+/// Stores the history of a source entry, replacing any previous one.
+pub fn insert_history(conn: &Connection, source_entry_id: &str, history: &History) -> Result<()> {
+    conn.execute(
+        r#"
+        INSERT OR REPLACE INTO history
+            (source_entry_id, created, updated, authors)
+        VALUES
+            (?1, ?2, ?3, ?4)
+        "#,
+        params![
+            source_entry_id,
+            history.created.to_rfc3339(),
+            history.updated.to_rfc3339(),
+            serde_json::to_string(&history.authors)?,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Removes the history of every entry of a source.
+pub fn delete_history(conn: &Connection, source_id: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM history WHERE source_entry_id IN (SELECT id FROM source_entry WHERE source_id = ?1)",
+        params![source_id],
+    )?;
+
+    Ok(())
+}
+
+/// Removes a source, which is expected to have no entries left.
+pub fn delete_source(conn: &Connection, source_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM source WHERE id = ?1", params![source_id])?;
+
+    Ok(())
+}
+
+/// Stores the front matter metadata of a source entry along with its original format.
+pub fn insert_front_matter(
+    conn: &Connection,
+    entry: &SourceEntry,
+    document: &Document,
+) -> Result<()> {
+    conn.execute(
+        r#"
+        INSERT OR REPLACE INTO front_matter
+            (source_entry_id, format, metadata)
+        VALUES
+            (?1, ?2, ?3)
+        "#,
+        params![
+            entry.id(),
+            document.format.map(|format| format.as_str()),
+            front_matter::to_json(&document.metadata),
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Fetches the front matter metadata of a source entry, if any.
+pub fn get_front_matter(conn: &Connection, id: &str) -> Result<Option<Metadata>> {
+    let mut stmt = conn.prepare("SELECT metadata FROM front_matter WHERE source_entry_id = ?1")?;
+    let mut rows = stmt.query(params![id])?;
+
+    match rows.next()? {
+        None => Ok(None),
+        Some(row) => {
+            let json: String = row.get(0)?;
+
+            Ok(Some(front_matter::from_json(&json)?))
+        }
+    }
+}
+
+/// Removes any content no longer referenced by a source entry.
+pub fn delete_orphan_content(conn: &Connection) -> Result<usize> {
+    let count = conn.execute(
+        "DELETE FROM content WHERE id NOT IN (SELECT content_id FROM source_entry)",
+        params![],
+    )?;
+
+    Ok(count)
+}
+
+/// Fetches the blob of a piece of content.
+pub fn get_content(conn: &Connection, id: &str) -> Result<Blob> {
+    let blob = conn.query_row(
+        "SELECT content FROM content WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )?;
+
+    Ok(blob)
+}
+
+/// A source entry whose node is missing or out of date.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaleEntry {
+    pub id: String,
+    pub path: String,
+    pub content_id: String,
+}
+
+/// Fetches every Markdown source entry without an up to date node.
+pub fn get_stale_entries(conn: &Connection) -> Result<Vec<StaleEntry>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            source_entry.id,
+            source_entry.path,
+            source_entry.content_id
+        FROM
+            source_entry
+        LEFT JOIN
+            node ON node.id = source_entry.id
+        WHERE
+            source_entry.content_type_id = 'text/markdown'
+            AND (node.id IS NULL OR node.content_id != source_entry.content_id)
+        ORDER BY 1
+        "#,
+    )?;
+    let rows = stmt.query_map(params![], |row| {
+        Ok(StaleEntry {
+            id: row.get(0)?,
+            path: row.get(1)?,
+            content_id: row.get(2)?,
+        })
+    })?;
+    let mut entries = Vec::new();
+
+    for row in rows {
+        entries.push(row?);
+    }
+
+    Ok(entries)
+}
+
+/// Marks every node as stale so it is extracted again.
+pub fn invalidate_nodes(conn: &Connection) -> Result<()> {
+    conn.execute("UPDATE node SET content_id = ''", params![])?;
+
+    Ok(())
+}
+
+/// Stores a node, replacing any previous record with the same identifier.
+pub fn insert_node(conn: &Connection, node: &Node) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO node (id, title, content_id) VALUES (?1, ?2, ?3)",
+        params![node.id(), node.title(), node.content_id().to_string()],
+    )?;
+
+    Ok(())
+}
+
+/// Stores a connection.
+///
+/// Fails if the origin already has a connection of the same type starting at the same offset.
+pub fn insert_connection(conn: &Connection, connection: &NodeConnection) -> Result<()> {
+    conn.execute(
+        r#"
+        INSERT INTO connection
+            (origin_id, target, title, connection_type_id, span_start, span_end)
+        VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6)
+        "#,
+        params![
+            connection.origin_id(),
+            connection.target(),
+            connection.title(),
+            connection.connection_type().as_str(),
+            connection.span().start as i64,
+            connection.span().end as i64,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Removes every connection originating from the given node.
+pub fn delete_connections(conn: &Connection, origin_id: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM connection WHERE origin_id = ?1",
+        params![origin_id],
+    )?;
+
+    Ok(())
+}
+
+/// Fetches the content of a source entry.
+pub fn get_entry_content(conn: &Connection, id: &str) -> Result<Blob> {
+    let blob = conn.query_row(
+        r#"
+        SELECT
+            content.content
+        FROM
+            source_entry
+        JOIN
+            content ON content.id = source_entry.content_id
+        WHERE
+            source_entry.id = ?1
+        "#,
+        params![id],
+        |row| row.get(0),
+    )?;
+
+    Ok(blob)
+}
+
+/// Fetches the route of every known source keyed by source identifier.
+pub fn get_source_routes(conn: &Connection) -> Result<HashMap<String, PathBuf>> {
+    let mut stmt = conn.prepare("SELECT id, route FROM source")?;
+    let rows = stmt.query_map(params![], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    let mut routes = HashMap::new();
+
+    for row in rows {
+        let (id, route) = row?;
+        routes.insert(id, PathBuf::from(route));
+    }
+
+    Ok(routes)
+}
+
+/// Fetches the identifiers of every known source.
+pub fn get_source_ids(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT id FROM source ORDER BY 1")?;
+    let rows = stmt.query_map(params![], |row| row.get(0))?;
+    let mut ids = Vec::new();
+
+    for row in rows {
+        ids.push(row?);
+    }
+
+    Ok(ids)
+}
+
+/// The keys a node can be referred to by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeKeys {
+    pub id: String,
+    pub source_id: String,
+    pub path: String,
+    pub title: String,
+    /// The `id` found in the front matter, if any.
+    pub front_matter_id: Option<String>,
+}
+
+/// Fetches the keys of every node.
+pub fn get_node_keys(conn: &Connection) -> Result<Vec<NodeKeys>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            node.id,
+            source_entry.source_id,
+            source_entry.path,
+            node.title,
+            front_matter.metadata
+        FROM
+            node
+        JOIN
+            source_entry ON source_entry.id = node.id
+        LEFT JOIN
+            front_matter ON front_matter.source_entry_id = node.id
+        ORDER BY 1
+        "#,
+    )?;
+    let rows = stmt.query_map(params![], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, Option<String>>(4)?,
+        ))
+    })?;
+    let mut keys = Vec::new();
+
+    for row in rows {
+        let (id, source_id, path, title, metadata) = row?;
+        let front_matter_id = match metadata {
+            Some(json) => front_matter::from_json(&json)?
+                .get("id")
+                .and_then(|id| id.to_key()),
+            None => None,
+        };
+
+        keys.push(NodeKeys {
+            id,
+            source_id,
+            path,
+            title,
+            front_matter_id,
+        });
+    }
+
+    Ok(keys)
+}
+
+/// A connection target as recorded in the cache along with the origin information needed to
+/// resolve it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionTarget {
+    pub origin_id: String,
+    pub origin_path: String,
+    pub source_id: String,
+    pub target: String,
+    pub connection_type: ConnectionType,
+    pub span: Range<usize>,
+}
+
+/// Fetches every connection target.
+pub fn get_connection_targets(conn: &Connection) -> Result<Vec<ConnectionTarget>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            connection.origin_id,
+            source_entry.path,
+            source_entry.source_id,
+            connection.target,
+            connection.connection_type_id,
+            connection.span_start,
+            connection.span_end
+        FROM
+            connection
+        JOIN
+            source_entry ON source_entry.id = connection.origin_id
+        ORDER BY 1, 6
+        "#,
+    )?;
+    let rows = stmt.query_map(params![], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, i64>(5)?,
+            row.get::<_, i64>(6)?,
+        ))
+    })?;
+    let mut targets = Vec::new();
+
+    for row in rows {
+        let (origin_id, origin_path, source_id, target, connection_type, start, end) = row?;
+
+        targets.push(ConnectionTarget {
+            origin_id,
+            origin_path,
+            source_id,
+            target,
+            connection_type: connection_type.parse()?,
+            span: start as usize..end as usize,
+        });
+    }
+
+    Ok(targets)
+}
+
+/// Sets the node a connection resolves to.
+pub fn update_connection_target(
+    conn: &Connection,
+    origin_id: &str,
+    span_start: usize,
+    connection_type: &ConnectionType,
+    target_id: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        r#"
+        UPDATE connection SET target_id = ?4
+        WHERE origin_id = ?1 AND span_start = ?2 AND connection_type_id = ?3
+        "#,
+        params![
+            origin_id,
+            span_start as i64,
+            connection_type.as_str(),
+            target_id
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Finds the node extracted from the entry with the given path in the given source.
+pub fn get_node_id(conn: &Connection, source_id: &str, path: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            node.id
+        FROM
+            node
+        JOIN
+            source_entry ON source_entry.id = node.id
+        WHERE
+            source_entry.source_id = ?1
+            AND source_entry.path = ?2
+        "#,
+    )?;
+    let mut rows = stmt.query(params![source_id, path])?;
+
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// A connection pointing to a node along with the origin information needed to present it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncomingConnection {
+    pub origin_id: String,
+    pub source_id: String,
+    pub path: String,
+    pub title: String,
+    pub span: Range<usize>,
+}
 
-    conn.execute_batch(&bootstrap)?;
+/// Fetches every connection resolved to the given node.
+pub fn get_incoming_connections(
+    conn: &Connection,
+    target_id: &str,
+) -> Result<Vec<IncomingConnection>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            connection.origin_id,
+            source_entry.source_id,
+            source_entry.path,
+            node.title,
+            connection.span_start,
+            connection.span_end
+        FROM
+            connection
+        JOIN
+            node ON node.id = connection.origin_id
+        JOIN
+            source_entry ON source_entry.id = connection.origin_id
+        WHERE
+            connection.target_id = ?1
+        ORDER BY 2, 3, 5
+        "#,
+    )?;
+    let rows = stmt.query_map(params![target_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, i64>(5)?,
+        ))
+    })?;
+    let mut connections = Vec::new();
+
+    for row in rows {
+        let (origin_id, source_id, path, title, start, end) = row?;
+
+        connections.push(IncomingConnection {
+            origin_id,
+            source_id,
+            path,
+            title,
+            span: start as usize..end as usize,
+        });
+    }
+
+    Ok(connections)
+}
+
+/// Fetches every node along with its source entry and front matter, sorted by source and path.
+pub fn get_graph_nodes(conn: &Connection) -> Result<Vec<GraphNode>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            node.id,
+            source_entry.source_id,
+            source_entry.path,
+            node.title,
+            front_matter.metadata,
+            node.content_id,
+            history.created,
+            history.updated,
+            history.authors
+        FROM
+            node
+        JOIN
+            source_entry ON source_entry.id = node.id
+        LEFT JOIN
+            front_matter ON front_matter.source_entry_id = node.id
+        LEFT JOIN
+            history ON history.source_entry_id = node.id
+        ORDER BY 2, 3
+        "#,
+    )?;
+    let rows = stmt.query_map(params![], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, String>(5)?,
+            row.get::<_, Option<String>>(6)?,
+            row.get::<_, Option<String>>(7)?,
+            row.get::<_, Option<String>>(8)?,
+        ))
+    })?;
+    let mut nodes = Vec::new();
+
+    for row in rows {
+        let (id, source_id, path, title, metadata, content_id, created, updated, authors) = row?;
+        let metadata = match metadata {
+            Some(json) => front_matter::from_json(&json)?,
+            None => Metadata::new(),
+        };
+        let history = match (created, updated, authors) {
+            (Some(created), Some(updated), Some(authors)) => Some(History {
+                created: DateTime::parse_from_rfc3339(&created)?.with_timezone(&Utc),
+                updated: DateTime::parse_from_rfc3339(&updated)?.with_timezone(&Utc),
+                authors: serde_json::from_str(&authors)?,
+            }),
+            _ => None,
+        };
+
+        nodes.push(GraphNode {
+            id,
+            source_id,
+            path,
+            title,
+            metadata,
+            content_id,
+            history,
+        });
+    }
+
+    Ok(nodes)
+}
+
+/// Fetches every connection resolved to a node, sorted by origin and position.
+pub fn get_graph_edges(conn: &Connection) -> Result<Vec<Edge>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            origin_id,
+            target_id,
+            connection_type_id,
+            title,
+            span_start,
+            span_end
+        FROM
+            connection
+        WHERE
+            target_id IS NOT NULL
+        ORDER BY origin_id, span_start
+        "#,
+    )?;
+    let rows = stmt.query_map(params![], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, i64>(5)?,
+        ))
+    })?;
+    let mut edges = Vec::new();
+
+    for row in rows {
+        let (origin_id, target_id, connection_type, title, start, end) = row?;
+
+        edges.push(Edge {
+            origin_id,
+            target_id,
+            connection_type: connection_type.parse()?,
+            title,
+            span: start as usize..end as usize,
+        });
+    }
+
+    Ok(edges)
+}
+
+/// Fetches the checksum of the data an output file was rendered from, if any.
+pub fn get_output_checksum(conn: &Connection, id: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare("SELECT checksum FROM output WHERE id = ?1")?;
+    let mut rows = stmt.query(params![id])?;
+
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// Stores an output file, replacing any previous record with the same identifier.
+pub fn insert_output(conn: &Connection, id: &str, template_id: &str, checksum: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO output (id, template_id, checksum) VALUES (?1, ?2, ?3)",
+        params![id, template_id, checksum],
+    )?;
+
+    Ok(())
+}
+
+/// Fetches the identifiers of every output file.
+pub fn get_output_ids(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT id FROM output ORDER BY 1")?;
+    let rows = stmt.query_map(params![], |row| row.get(0))?;
+    let mut ids = Vec::new();
+
+    for row in rows {
+        ids.push(row?);
+    }
+
+    Ok(ids)
+}
+
+/// Forgets the checksum of the output files rendered with the given template or of every output
+/// file if none is given so they are rendered again.
+pub fn invalidate_outputs(conn: &Connection, template_id: Option<&str>) -> Result<usize> {
+    let count = match template_id {
+        Some(template_id) => conn.execute(
+            "UPDATE output SET checksum = '' WHERE template_id = ?1",
+            params![template_id],
+        )?,
+        None => conn.execute("UPDATE output SET checksum = ''", params![])?,
+    };
+
+    Ok(count)
+}
+
+/// Removes an output file.
+pub fn delete_output(conn: &Connection, id: &str) -> Result<()> {
+    conn.execute("DELETE FROM output WHERE id = ?1", params![id])?;
 
     Ok(())
 }
 
+/// Formats a stamp time with full precision so it can be compared as text.
+fn format_stamp_time(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::filesystem::tests::TempDir;
     use rusqlite::NO_PARAMS;
 
+    #[test]
+    fn open_existing_cache() -> Result<()> {
+        let dir = TempDir::new("cache-open")?;
+        let missing = open(dir.join("missing.db")).map(|_| ()).unwrap_err();
+        connect(dir.join("empty.db"))?;
+        let empty = open(dir.join("empty.db")).map(|_| ()).unwrap_err();
+        bootstrap(&connect(dir.join("built.db"))?)?;
+
+        assert!(missing.to_string().starts_with("No cache at"));
+        assert!(missing.to_string().ends_with("run `onelo build` first"));
+        assert!(!dir.join("missing.db").exists());
+        assert!(empty.to_string().starts_with("Outdated cache at"));
+        assert!(open(dir.join("built.db")).is_ok());
+
+        Ok(())
+    }
+
     #[test]
     fn bootstrap_cache() -> Result<()> {
         let mut conn = connect(":memory:")?;
@@ -63,7 +972,28 @@ mod tests {
             actual.push(row?);
         }
 
-        assert_eq!(actual.len(), 5);
+        assert_eq!(actual.len(), 11);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bootstrap_outdated_cache() -> Result<()> {
+        let conn = connect(":memory:")?;
+        conn.execute_batch(
+            "CREATE TABLE source_entry (id text NOT NULL PRIMARY KEY); PRAGMA user_version = 1;",
+        )?;
+
+        bootstrap(&conn)?;
+
+        let version: i64 = conn.query_row("PRAGMA user_version", NO_PARAMS, |row| row.get(0))?;
+        let entries: i64 =
+            conn.query_row("SELECT count(digest) FROM source_entry", NO_PARAMS, |row| {
+                row.get(0)
+            })?;
+
+        assert_eq!(version, schema_version());
+        assert_eq!(entries, 0);
 
         Ok(())
     }
@@ -0,0 +1,244 @@
+//! This module is concerned with checking the integrity of the graph.
+//!
+//! A check reports malformed front matters, connections whose target doesn't resolve to a node,
+//! ambiguous wikilinks and front matter identifiers used by more than one entry.
+
+use crate::cache;
+use crate::context::Result;
+use crate::front_matter;
+use crate::pipeline;
+use rusqlite::Connection;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+/// A problem found in a source entry.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+/// The error returned when a check finds problems.
+#[derive(Debug)]
+pub struct CheckError(pub Vec<Diagnostic>);
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.0 {
+            writeln!(f, "{}", diagnostic)?;
+        }
+
+        write!(f, "{} problems found.", self.0.len())
+    }
+}
+
+impl Error for CheckError {}
+
+/// Collects the problems found in the cache given the outcome of a lenient pipeline run.
+///
+/// Diagnostics are sorted by path, line and column.
+pub fn run(conn: &Connection, report: &pipeline::Report) -> Result<Vec<Diagnostic>> {
+    let routes = cache::get_source_routes(conn)?;
+    let keys = cache::get_node_keys(conn)?;
+    let locations: HashMap<&str, PathBuf> = keys
+        .iter()
+        .map(|keys| {
+            let route = routes.get(&keys.source_id).cloned().unwrap_or_default();
+
+            (keys.id.as_str(), route.join(&keys.path))
+        })
+        .collect();
+    let mut contents: HashMap<String, String> = HashMap::new();
+    let mut diagnostics = Vec::new();
+
+    for invalid in &report.sourcing.invalid {
+        let (line, column) = invalid.error.position();
+
+        diagnostics.push(Diagnostic {
+            path: PathBuf::from(&invalid.location),
+            line,
+            column,
+            message: invalid.error.message(),
+        });
+    }
+
+    for unresolved in &report.resolution.unresolved {
+        let content = entry_content(conn, &mut contents, &unresolved.origin_id)?;
+        let (line, column) = position(content, unresolved.span.start);
+        let message = if unresolved.is_ambiguous() {
+            format!(
+                "Ambiguous target `{}` matches {}",
+                unresolved.target,
+                quote_all(&unresolved.candidates)
+            )
+        } else {
+            format!("Unresolved target `{}`", unresolved.target)
+        };
+
+        diagnostics.push(Diagnostic {
+            path: locations
+                .get(unresolved.origin_id.as_str())
+                .cloned()
+                .unwrap_or_default(),
+            line,
+            column,
+            message,
+        });
+    }
+
+    let mut ids: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+    for keys in &keys {
+        if let Some(front_matter_id) = &keys.front_matter_id {
+            ids.entry(front_matter_id).or_default().push(&keys.id);
+        }
+    }
+
+    for (front_matter_id, entries) in ids.iter().filter(|(_, entries)| entries.len() > 1) {
+        for entry in entries {
+            let content = entry_content(conn, &mut contents, entry)?;
+            let (line, column) = front_matter::key_position(content, "id").unwrap_or((1, 1));
+            let others: Vec<String> = entries
+                .iter()
+                .filter(|other| other != &entry)
+                .map(|other| other.to_string())
+                .collect();
+
+            diagnostics.push(Diagnostic {
+                path: locations.get(entry).cloned().unwrap_or_default(),
+                line,
+                column,
+                message: format!(
+                    "Duplicate front matter id `{}` also used by {}",
+                    front_matter_id,
+                    quote_all(&others)
+                ),
+            });
+        }
+    }
+
+    diagnostics.sort();
+
+    Ok(diagnostics)
+}
+
+/// Fetches the content of an entry as text, memoised given that an entry typically has more than
+/// one problem.
+fn entry_content<'a>(
+    conn: &Connection,
+    contents: &'a mut HashMap<String, String>,
+    id: &str,
+) -> Result<&'a str> {
+    if !contents.contains_key(id) {
+        let blob = cache::get_entry_content(conn, id)?;
+        contents.insert(id.to_string(), String::from_utf8(blob)?);
+    }
+
+    Ok(&contents[id])
+}
+
+/// Computes the line and column (1-based) of a byte offset. Columns count characters, not bytes.
+pub fn position(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+
+    (line, column)
+}
+
+fn quote_all(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("`{}`", item))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+    use crate::source::Source;
+    use std::fs;
+
+    #[test]
+    fn position_of_offset() {
+        let content = "ab\ncdé\nf";
+
+        assert_eq!(position(content, 0), (1, 1));
+        assert_eq!(position(content, 4), (2, 2));
+        assert_eq!(position(content, 7), (2, 4));
+        assert_eq!(position(content, 8), (3, 1));
+    }
+
+    #[test]
+    fn check_problems() -> Result<()> {
+        let dir = TempDir::new("check")?;
+        fs::write(
+            dir.join("a.md"),
+            "+++\nid = \"same\"\n+++\nLorem [ipsum](b.md).\n\nSee [[dup]] and [c](c.md).\n",
+        )?;
+        fs::write(
+            dir.join("b.md"),
+            "+++\nid = \"same\"\ntitle = \"Dup\"\n+++\n",
+        )?;
+        fs::write(dir.join("dup.md"), "")?;
+        fs::write(dir.join("invalid.md"), "+++\ntitle = \n+++\n")?;
+        fs::write(dir.join("unclosed.md"), "---\ntitle: Open\n")?;
+
+        let mut conn = cache::connect(":memory:")?;
+        let source = Source::new("test".parse()?, &dir);
+        let report = pipeline::run(&mut conn, &[source], &Default::default())?;
+        let diagnostics = run(&conn, &report)?;
+        let actual: Vec<String> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                format!(
+                    "{}:{}:{}",
+                    diagnostic.path.file_name().unwrap().to_string_lossy(),
+                    diagnostic.line,
+                    diagnostic.column
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            actual,
+            vec![
+                "a.md:2:1",
+                "a.md:6:5",
+                "a.md:6:17",
+                "b.md:2:1",
+                "invalid.md:2:9",
+                "unclosed.md:1:1"
+            ]
+        );
+        assert_eq!(
+            diagnostics[5].message,
+            "The front matter is missing its closing `---`"
+        );
+        assert!(report
+            .to_string()
+            .contains("invalid.md:2:9: expected a value, found a newline"));
+
+        Ok(())
+    }
+}
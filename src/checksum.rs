@@ -1,11 +1,13 @@
 //! This module is concerned with the artefact checksums.
 
 use arrayvec::ArrayString;
-use blake3::{self, Hash, OUT_LEN};
+use blake3::join::RayonJoin;
+use blake3::{self, Hash, Hasher, OUT_LEN};
 use std::array::TryFromSliceError;
 use std::convert::TryInto;
 use std::error::Error;
 use std::fmt;
+use std::io::{self, Read};
 use std::str::FromStr;
 
 /// The multihash code.
@@ -15,6 +17,14 @@ pub type Length = u8;
 const BLAKE3_CODE: Code = 0x1e;
 const BLAKE3_LEN: Length = 0x20;
 
+/// The input size from which hashing is spread over several threads. Below it the overhead
+/// outweighs the gain.
+const PARALLEL_THRESHOLD: usize = 128 * 1024;
+
+/// The size of the pieces hashed at a time by `from_reader`, a multiple of the BLAKE3 chunk size
+/// so every piece can be spread over several threads.
+const READ_CHUNK: u64 = 1024 * 1024;
+
 pub type Byteset = [u8; OUT_LEN];
 pub type Hex = ArrayString<[u8; 64]>;
 
@@ -28,7 +38,7 @@ pub struct Checksum {
 }
 
 impl Checksum {
-    /// Hashes the given bytes.
+    /// Hashes the given bytes, on several threads for large inputs.
     ///
     /// If you need to cast a `Byteset` as a `Checksum` use the `From` implementations.
     ///
@@ -43,7 +53,38 @@ impl Checksum {
     /// assert_eq!(chksum.to_string(), expected);
     /// ```
     pub fn new(input: &[u8]) -> Self {
-        Self::wrap(blake3::hash(input))
+        if input.len() < PARALLEL_THRESHOLD {
+            return Self::wrap(blake3::hash(input));
+        }
+
+        let mut hasher = Hasher::new();
+        hasher.update_with_join::<RayonJoin>(input);
+
+        Self::wrap(hasher.finalize())
+    }
+
+    /// Hashes everything read from the given reader while appending it to `blob`, so the input is
+    /// hashed piece by piece as it arrives instead of once it is whole.
+    pub fn from_reader<R: Read>(mut reader: R, blob: &mut Vec<u8>) -> io::Result<Self> {
+        let mut hasher = Hasher::new();
+
+        loop {
+            let start = blob.len();
+
+            if reader.by_ref().take(READ_CHUNK).read_to_end(blob)? == 0 {
+                break;
+            }
+
+            let piece = &blob[start..];
+
+            if piece.len() < PARALLEL_THRESHOLD {
+                hasher.update(piece);
+            } else {
+                hasher.update_with_join::<RayonJoin>(piece);
+            }
+        }
+
+        Ok(Self::wrap(hasher.finalize()))
     }
 
     pub fn as_bytes(&self) -> &[u8; 32] {
@@ -58,7 +99,7 @@ impl Checksum {
         Self {
             code: BLAKE3_CODE,
             len: BLAKE3_LEN,
-            hash: hash.into(),
+            hash,
         }
     }
 
@@ -165,6 +206,25 @@ mod tests {
         assert_eq!(actual.to_string(), expected);
     }
 
+    #[test]
+    fn blake3_checksum_large_input() {
+        let input = vec![7; PARALLEL_THRESHOLD * 3 + 1];
+
+        assert_eq!(Checksum::new(&input).unwrap(), blake3::hash(&input));
+    }
+
+    #[test]
+    fn blake3_checksum_from_reader() -> io::Result<()> {
+        let input: Vec<u8> = (0..READ_CHUNK * 2 + 7).map(|i| i as u8).collect();
+        let mut blob = Vec::new();
+        let checksum = Checksum::from_reader(&input[..], &mut blob)?;
+
+        assert_eq!(checksum, Checksum::new(&input));
+        assert_eq!(blob, input);
+
+        Ok(())
+    }
+
     #[test]
     fn parse() -> Result<(), ChecksumError> {
         let hex = "1e20cabe0427e7fdaa13ec1d49de58a6179a2ecb6dd6fd674261421949fab0acc525";
@@ -0,0 +1,39 @@
+use crate::backlinks;
+use crate::cache;
+use crate::config;
+use crate::context::{Message, Result};
+use crate::source_entry::SourceEntry;
+use clap::Clap;
+use std::path::PathBuf;
+
+/// Lists the nodes linking to the given entry, e.g. `unnamed:01.md`.
+#[derive(Debug, Clap)]
+pub struct Cmd {
+    /// Entry as `source:path`
+    #[clap(value_name = "entry")]
+    entry: SourceEntry,
+    /// Configuration file [default: onelo.toml in the working directory or its ancestors]
+    #[clap(long, value_name = "path")]
+    config: Option<PathBuf>,
+    /// Cache path [default: onelo.db]
+    #[clap(long, short = 'c', value_name = "path")]
+    cache_path: Option<PathBuf>,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<Message> {
+        let cache_path = match &self.cache_path {
+            Some(cache_path) => cache_path.clone(),
+            None => config::find(self.config.as_deref())?.cache_path,
+        };
+        let conn = cache::open(&cache_path)?;
+        let backlinks = backlinks::find(&conn, &self.entry)?;
+        if backlinks.is_empty() {
+            return Ok("No backlinks found.".into());
+        }
+
+        let lines: Vec<String> = backlinks.iter().map(|b| b.to_string()).collect();
+
+        Ok(lines.join("\n"))
+    }
+}
@@ -1,26 +1,108 @@
+use crate::cache;
+use crate::cli::source::SourceArgs;
+use crate::config::{Config, Output};
 use crate::context::{Message, Result};
-use crate::filesystem;
+use crate::pipeline;
+use crate::transformation::{self, Format, Options};
+use crate::watch;
+use chrono::prelude::*;
 use clap::Clap;
+use rusqlite::Connection;
 use std::path::PathBuf;
 
 /// Builds the onelo store.
 #[derive(Debug, Clap)]
 pub struct Cmd {
-    /// Input path
-    #[clap(long, short = 'i', value_name = "path", default_value = "./test/files")]
-    input_path: PathBuf,
-    /// Cache path
-    #[clap(long, short = 'c', value_name = "path", default_value = "./onelo.db")]
-    cache_path: PathBuf,
+    #[clap(flatten)]
+    source: SourceArgs,
+    /// Cache path [default: onelo.db]
+    #[clap(long, short = 'c', value_name = "path")]
+    cache_path: Option<PathBuf>,
+    /// Output format (dot, json, graphml, gexf, html), replacing the configured outputs
+    #[clap(long, short = 'f', value_name = "format", requires = "output-path")]
+    format: Option<Format>,
+    /// Output path
+    #[clap(long, short = 'o', value_name = "path", requires = "format")]
+    output_path: Option<PathBuf>,
+    /// Groups the nodes of each source together [default: false]
+    #[clap(long, value_name = "bool", parse(try_from_str))]
+    cluster: Option<bool>,
+    /// HTML templates path
+    #[clap(long, short = 't', value_name = "path")]
+    templates_path: Option<PathBuf>,
+    /// Keeps running and rebuilds whenever a source changes
+    #[clap(long)]
+    watch: bool,
 }
 
 impl Cmd {
     pub fn run(&self) -> Result<Message> {
-        let paths = filesystem::get_files(&self.input_path)?;
+        let mut config = self.source.config()?;
 
-        dbg!(&paths);
-        let message = format!("{} files parsed.", paths.len());
+        if let Some(cache_path) = &self.cache_path {
+            config.cache_path = cache_path.clone();
+        }
 
-        Ok(message)
+        if let (Some(format), Some(output_path)) = (self.format, &self.output_path) {
+            config.outputs = vec![Output {
+                format,
+                path: output_path.clone(),
+                options: Options::default(),
+            }];
+        }
+
+        for output in &mut config.outputs {
+            if let Some(cluster) = self.cluster {
+                output.options.cluster = cluster;
+            }
+
+            if let Some(templates_path) = &self.templates_path {
+                output.options.templates_path = Some(templates_path.clone());
+            }
+        }
+
+        let mut conn = cache::connect(&config.cache_path)?;
+        let message = build(&mut conn, &config)?;
+
+        if !self.watch {
+            return Ok(message);
+        }
+
+        println!("{}\nWatching for changes…", message);
+
+        let mut ignored = vec![config.cache_path.clone()];
+        ignored.extend(config.outputs.iter().map(|output| output.path.clone()));
+
+        watch::run(&config.sources, &ignored, || {
+            let time = Local::now().format("%H:%M:%S");
+
+            match build(&mut conn, &config) {
+                Ok(message) => println!("[{}] {}", time, message.replace('\n', " ")),
+                Err(err) => eprintln!("[{}] {}", time, err),
+            }
+        })?;
+
+        Ok(Message::new())
+    }
+}
+
+/// Runs the pipeline and writes every output.
+fn build(conn: &mut Connection, config: &Config) -> Result<Message> {
+    let report = pipeline::run(conn, &config.sources, &config.extractors)?;
+    let mut message = report.to_string();
+
+    cache::clean(conn)?;
+
+    for output in &config.outputs {
+        let report = transformation::run(conn, output.format, &output.options, &output.path)?;
+
+        message.push_str(&format!(
+            "\nGraph written to {} as {}: {}",
+            output.path.display(),
+            output.format,
+            report
+        ));
     }
+
+    Ok(message)
 }
@@ -0,0 +1,31 @@
+use crate::cache;
+use crate::check::{self, CheckError};
+use crate::cli::source::SourceArgs;
+use crate::context::{Message, Result};
+use crate::pipeline;
+use clap::Clap;
+
+/// Checks the sources for malformed front matters, broken links, ambiguous wikilinks and
+/// duplicate identifiers.
+///
+/// The cache is kept in memory so nothing is written.
+#[derive(Debug, Clap)]
+pub struct Cmd {
+    #[clap(flatten)]
+    source: SourceArgs,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<Message> {
+        let mut conn = cache::connect(":memory:")?;
+        let config = self.source.config()?;
+        let report = pipeline::run(&mut conn, &config.sources, &config.extractors)?;
+        let diagnostics = check::run(&conn, &report)?;
+
+        if !diagnostics.is_empty() {
+            return Err(Box::new(CheckError(diagnostics)));
+        }
+
+        Ok("No problems found.".into())
+    }
+}
@@ -0,0 +1,37 @@
+use crate::cli::source::SourceArgs;
+use crate::context::{Message, Result};
+use crate::diff;
+use clap::Clap;
+
+/// Compares the graphs built from the Git repository of every source at two revisions.
+///
+/// Both graphs are built in memory so nothing is written.
+#[derive(Debug, Clap)]
+pub struct Cmd {
+    /// Revision to compare from, e.g. a branch, a tag or a commit hash
+    #[clap(value_name = "rev-a")]
+    before: String,
+    /// Revision to compare to
+    #[clap(value_name = "rev-b")]
+    after: String,
+    #[clap(flatten)]
+    source: SourceArgs,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<Message> {
+        let config = self.source.config()?;
+        let diff = diff::run(
+            &config.sources,
+            &config.extractors,
+            &self.before,
+            &self.after,
+        )?;
+
+        if diff.is_empty() {
+            return Ok("No changes found.".into());
+        }
+
+        Ok(diff.to_string())
+    }
+}
@@ -1 +1,6 @@
+pub mod backlinks;
 pub mod build;
+pub mod check;
+pub mod diff;
+pub mod serve;
+pub mod source;
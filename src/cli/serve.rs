@@ -0,0 +1,117 @@
+use crate::cache;
+use crate::cli::source::SourceArgs;
+use crate::context::{Message, Result};
+use crate::pipeline;
+use crate::serve::{Server, DEFAULT_ADDRESS};
+use crate::transformation::{self, Format};
+use crate::watch;
+use chrono::prelude::*;
+use clap::Clap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::{env, fs, process, thread};
+
+/// Serves the HTML site over HTTP and rebuilds it whenever a source changes.
+///
+/// The cache is kept in memory and the site is written to a temporary directory, removed on exit,
+/// so nothing is written next to the sources. Served pages reload after every rebuild.
+#[derive(Debug, Clap)]
+pub struct Cmd {
+    #[clap(flatten)]
+    source: SourceArgs,
+    /// Address to listen on
+    #[clap(long, short = 'a', value_name = "host:port", default_value = DEFAULT_ADDRESS)]
+    address: String,
+    /// HTML templates path
+    #[clap(long, short = 't', value_name = "path")]
+    templates_path: Option<PathBuf>,
+}
+
+impl Cmd {
+    pub fn run(&self) -> Result<Message> {
+        let config = self.source.config()?;
+        let mut options = config
+            .outputs
+            .iter()
+            .find(|output| output.format == Format::Html)
+            .map(|output| output.options.clone())
+            .unwrap_or_default();
+
+        if let Some(templates_path) = &self.templates_path {
+            options.templates_path = Some(templates_path.clone());
+        }
+
+        let site = SiteDir::new(env::temp_dir().join(format!("onelo-serve-{}", process::id())))?;
+        let site_path = site.0.clone();
+        let rebuilds = Arc::new(RwLock::new(0));
+        let mut conn = cache::connect(":memory:")?;
+        let mut build = || -> Result<Message> {
+            let report = pipeline::run(&mut conn, &config.sources, &config.extractors)?;
+            // Requests wait for the site to be written.
+            let mut rebuilds = rebuilds.write().map_err(|_| "Site lock poisoned")?;
+            let written = transformation::run(&conn, Format::Html, &options, &site_path)?;
+
+            *rebuilds += 1;
+
+            Ok(format!("{}\nSite written: {}", report, written))
+        };
+
+        let message = build()?;
+        let server = Server::bind(&self.address, &site_path, rebuilds.clone())?;
+        let cleanup = (rebuilds.clone(), site_path.clone());
+
+        ctrlc::set_handler(move || {
+            let (rebuilds, site_path) = &cleanup;
+            // Waits for any rebuild in progress so it doesn't write into a removed directory.
+            let _rebuilds = rebuilds.write();
+
+            SiteDir::remove(site_path);
+            process::exit(130);
+        })?;
+
+        println!(
+            "{}\nServing at http://{}/\nWatching for changes…",
+            message,
+            server.address()
+        );
+        thread::spawn(move || server.run());
+
+        watch::run(&config.sources, std::slice::from_ref(&site_path), || {
+            let time = Local::now().format("%H:%M:%S");
+
+            match build() {
+                Ok(message) => println!("[{}] {}", time, message.replace('\n', " ")),
+                Err(err) => eprintln!("[{}] {}", time, err),
+            }
+        })?;
+
+        Ok(Message::new())
+    }
+}
+
+/// The temporary directory the site is written to, removed when dropped.
+struct SiteDir(PathBuf);
+
+impl SiteDir {
+    /// Starts from an empty directory in case a previous process with the same id left one.
+    fn new(path: PathBuf) -> Result<Self> {
+        Self::remove(&path);
+        fs::create_dir_all(&path)?;
+
+        Ok(SiteDir(path))
+    }
+
+    fn remove(path: &Path) {
+        if path.exists() {
+            if let Err(err) = fs::remove_dir_all(path) {
+                eprintln!("{}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+impl Drop for SiteDir {
+    fn drop(&mut self) {
+        Self::remove(&self.0);
+    }
+}
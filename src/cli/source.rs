@@ -0,0 +1,93 @@
+use crate::config::{self, Config, SOURCE_ID};
+use crate::context::Result;
+use crate::source::Source;
+use clap::Clap;
+use std::path::PathBuf;
+
+// The options describing the sources to read from, overriding the configuration file. Not a doc
+// comment as clap would show it instead of the about of the commands flattening it.
+#[derive(Debug, Clap)]
+pub struct SourceArgs {
+    /// Configuration file [default: onelo.toml in the working directory or its ancestors]
+    #[clap(long, value_name = "path")]
+    config: Option<PathBuf>,
+    /// Input path, replacing the configured sources
+    #[clap(long, short = 'i', value_name = "path")]
+    input_path: Option<PathBuf>,
+    /// Source as `id=path`, replacing the configured sources. Can be given several times
+    #[clap(
+        long = "source",
+        short = 's',
+        value_name = "id=path",
+        number_of_values = 1,
+        conflicts_with = "input-path",
+        parse(try_from_str = parse_source)
+    )]
+    sources: Vec<Source>,
+    /// Reads every source from its Git repository at the given revision
+    #[clap(long, value_name = "ref")]
+    revision: Option<String>,
+    /// Walks subdirectories of the input path [default: true]
+    #[clap(long, value_name = "bool", parse(try_from_str))]
+    recursive: Option<bool>,
+    /// Includes files matching the glob [default: *.md, *.markdown]
+    #[clap(long, value_name = "glob", number_of_values = 1)]
+    include: Vec<String>,
+    /// Excludes files and directories matching the glob
+    #[clap(long, value_name = "glob", number_of_values = 1)]
+    exclude: Vec<String>,
+    /// Walks files ignored by `.gitignore` and `.oneloignore` files too
+    #[clap(long)]
+    no_ignore: bool,
+}
+
+impl SourceArgs {
+    /// Loads the configuration and applies the given flags to every source.
+    pub fn config(&self) -> Result<Config> {
+        let mut config = config::find(self.config.as_deref())?;
+
+        if let Some(input_path) = &self.input_path {
+            config.sources = vec![Source::new(SOURCE_ID.parse()?, input_path)];
+        }
+
+        if !self.sources.is_empty() {
+            config.sources = self.sources.clone();
+        }
+
+        for source in config.sources.iter_mut() {
+            let mut walk_options = source.walk_options().clone();
+
+            if let Some(recursive) = self.recursive {
+                walk_options.recursive = recursive;
+            }
+
+            if !self.include.is_empty() {
+                walk_options.include = self.include.clone();
+            }
+
+            if !self.exclude.is_empty() {
+                walk_options.exclude = self.exclude.clone();
+            }
+
+            if self.no_ignore {
+                walk_options.ignore = false;
+            }
+
+            *source = source.clone().with_walk_options(walk_options);
+
+            if let Some(revision) = &self.revision {
+                *source = source.clone().with_revision(revision.as_str());
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Parses a source given as `id=path`.
+fn parse_source(s: &str) -> Result<Source> {
+    match s.find('=') {
+        Some(i) => Ok(Source::new(s[..i].parse()?, &s[i + 1..])),
+        None => Err(format!("Expected a source as `id=path`, found `{}`", s).into()),
+    }
+}
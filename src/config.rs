@@ -0,0 +1,393 @@
+//! This module is concerned with the project configuration file, `onelo.toml`.
+//!
+//! The configuration file is looked up in the working directory and its ancestors. Paths in it are
+//! relative to the directory containing the file.
+//!
+//! A source with `git = true` or a `revision` is read from the Git repository at its route, at
+//! `HEAD` unless a revision is given. A source whose route is a `.tar`, `.tar.gz`, `.tgz` or
+//! `.zip` file is read from that archive.
+//!
+//! ```toml
+//! cache = "onelo.db"
+//! extractors = ["links", "wikilinks"]
+//!
+//! [[sources]]
+//! id = "notes"
+//! route = "notes"
+//! recursive = true
+//! include = ["*.md"]
+//! exclude = ["drafts/"]
+//! ignore = true
+//!
+//! # A Git repository read at a tag rather than from its working tree.
+//! [[sources]]
+//! id = "docs"
+//! route = "../docs"
+//! revision = "v1.0"
+//!
+//! [[outputs]]
+//! format = "html"
+//! path = "site"
+//! cluster = false
+//! templates = "templates"
+//! ```
+
+use crate::context::Result;
+use crate::extraction::Extractors;
+use crate::filesystem::WalkOptions;
+use crate::git::DEFAULT_REVISION;
+use crate::source::Source;
+use crate::transformation::{Format, Options};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::{Table, Value};
+
+/// The name of the project configuration file.
+pub const FILENAME: &str = "onelo.toml";
+
+/// The identifier given to the source of the default configuration.
+pub const SOURCE_ID: &str = "unnamed";
+
+/// The settings of a project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub cache_path: PathBuf,
+    pub sources: Vec<Source>,
+    pub extractors: Extractors,
+    pub outputs: Vec<Output>,
+}
+
+impl Config {
+    /// The configuration used for anything left unset: a single source with the given directory
+    /// as route and a cache in it.
+    pub fn new(root: &Path) -> Result<Self> {
+        Ok(Config {
+            cache_path: root.join("onelo.db"),
+            sources: vec![Source::new(SOURCE_ID.parse()?, root)],
+            extractors: Extractors::default(),
+            outputs: Vec::new(),
+        })
+    }
+}
+
+/// An output to write the graph to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    pub format: Format,
+    pub path: PathBuf,
+    pub options: Options,
+}
+
+/// Looks for the configuration file in the given directory and its ancestors.
+pub fn discover(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|ancestor| ancestor.join(FILENAME))
+        .find(|path| path.is_file())
+}
+
+/// Loads the configuration from the given file or, when none is given, from the file discovered
+/// from the working directory, falling back to the default configuration.
+pub fn find(path: Option<&Path>) -> Result<Config> {
+    let path = match path {
+        Some(path) => Some(path.to_path_buf()),
+        None => discover(&std::env::current_dir()?),
+    };
+
+    match path {
+        Some(path) => load(&path),
+        None => Config::new(Path::new(".")),
+    }
+}
+
+/// Loads the configuration from the given file.
+pub fn load(path: &Path) -> Result<Config> {
+    let text = fs::read_to_string(path).map_err(|err| ConfigError::new(path, err))?;
+    let root = match path.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+
+    Ok(parse(&text, root).map_err(|err| ConfigError::new(path, err))?)
+}
+
+/// Parses a configuration resolving its paths against the given root.
+pub fn parse(text: &str, root: &Path) -> Result<Config> {
+    let value: Value = text.parse()?;
+    let table = as_table(&value, "configuration")?;
+    let mut config = Config::new(root)?;
+
+    guard_keys(
+        table,
+        "configuration",
+        &["cache", "extractors", "sources", "outputs"],
+    )?;
+
+    if let Some(value) = table.get("cache") {
+        config.cache_path = root.join(as_str(value, "cache")?);
+    }
+
+    if let Some(value) = table.get("extractors") {
+        config.extractors = Extractors::from_names(&as_strings(value, "extractors")?)?;
+    }
+
+    if let Some(value) = table.get("sources") {
+        config.sources = as_array(value, "sources")?
+            .iter()
+            .map(|value| parse_source(value, root))
+            .collect::<Result<_>>()?;
+    }
+
+    if let Some(value) = table.get("outputs") {
+        config.outputs = as_array(value, "outputs")?
+            .iter()
+            .map(|value| parse_output(value, root))
+            .collect::<Result<_>>()?;
+    }
+
+    Ok(config)
+}
+
+fn parse_source(value: &Value, root: &Path) -> Result<Source> {
+    let table = as_table(value, "sources")?;
+    let mut walk_options = WalkOptions::default();
+
+    guard_keys(
+        table,
+        "sources",
+        &[
+            "id",
+            "route",
+            "git",
+            "revision",
+            "recursive",
+            "include",
+            "exclude",
+            "ignore",
+        ],
+    )?;
+
+    let id = as_str(required(table, "sources", "id")?, "id")?;
+    let route = as_str(required(table, "sources", "route")?, "route")?;
+
+    if let Some(value) = table.get("recursive") {
+        walk_options.recursive = as_bool(value, "recursive")?;
+    }
+
+    if let Some(value) = table.get("include") {
+        walk_options.include = as_strings(value, "include")?;
+    }
+
+    if let Some(value) = table.get("exclude") {
+        walk_options.exclude = as_strings(value, "exclude")?;
+    }
+
+    if let Some(value) = table.get("ignore") {
+        walk_options.ignore = as_bool(value, "ignore")?;
+    }
+
+    let source = Source::new(id.parse()?, root.join(route)).with_walk_options(walk_options);
+    let git = match table.get("git") {
+        Some(value) => as_bool(value, "git")?,
+        None => false,
+    };
+
+    match table.get("revision") {
+        Some(value) => Ok(source.with_revision(as_str(value, "revision")?)),
+        None if git => Ok(source.with_revision(DEFAULT_REVISION)),
+        None => Ok(source),
+    }
+}
+
+fn parse_output(value: &Value, root: &Path) -> Result<Output> {
+    let table = as_table(value, "outputs")?;
+    let mut options = Options::default();
+
+    guard_keys(
+        table,
+        "outputs",
+        &["format", "path", "cluster", "templates"],
+    )?;
+
+    let format = as_str(required(table, "outputs", "format")?, "format")?;
+    let path = as_str(required(table, "outputs", "path")?, "path")?;
+
+    if let Some(value) = table.get("cluster") {
+        options.cluster = as_bool(value, "cluster")?;
+    }
+
+    if let Some(value) = table.get("templates") {
+        options.templates_path = Some(root.join(as_str(value, "templates")?));
+    }
+
+    Ok(Output {
+        format: format.parse()?,
+        path: root.join(path),
+        options,
+    })
+}
+
+fn guard_keys(table: &Table, name: &str, known: &[&str]) -> Result<()> {
+    match table.keys().find(|key| !known.contains(&key.as_str())) {
+        Some(key) => Err(format!("Unknown key `{}` in {}", key, name).into()),
+        None => Ok(()),
+    }
+}
+
+fn required<'a>(table: &'a Table, name: &str, key: &str) -> Result<&'a Value> {
+    table
+        .get(key)
+        .ok_or_else(|| format!("Missing key `{}` in {}", key, name).into())
+}
+
+fn as_table<'a>(value: &'a Value, name: &str) -> Result<&'a Table> {
+    value
+        .as_table()
+        .ok_or_else(|| format!("Expected `{}` to be a table", name).into())
+}
+
+fn as_array<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("Expected `{}` to be an array", name).into())
+}
+
+fn as_str<'a>(value: &'a Value, name: &str) -> Result<&'a str> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("Expected `{}` to be a string", name).into())
+}
+
+fn as_bool(value: &Value, name: &str) -> Result<bool> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("Expected `{}` to be a boolean", name).into())
+}
+
+fn as_strings(value: &Value, name: &str) -> Result<Vec<String>> {
+    as_array(value, name)?
+        .iter()
+        .map(|value| as_str(value, name).map(String::from))
+        .collect()
+}
+
+/// An error found while loading a configuration file.
+#[derive(Debug)]
+pub struct ConfigError {
+    path: PathBuf,
+    message: String,
+}
+
+impl ConfigError {
+    fn new<E: fmt::Display>(path: &Path, err: E) -> Self {
+        ConfigError {
+            path: path.to_path_buf(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+
+    #[test]
+    fn parse_full_config() -> Result<()> {
+        let text = r#"
+cache = "build/onelo.db"
+extractors = ["wikilinks"]
+
+[[sources]]
+id = "notes"
+route = "notes"
+exclude = ["drafts/"]
+
+[[sources]]
+id = "wiki"
+route = "/srv/wiki"
+recursive = false
+git = true
+
+[[outputs]]
+format = "html"
+path = "site"
+templates = "templates"
+"#;
+        let config = parse(text, Path::new("project"))?;
+
+        assert_eq!(config.cache_path, Path::new("project/build/onelo.db"));
+        assert_eq!(config.extractors.names(), vec!["wikilinks"]);
+        assert_eq!(config.sources.len(), 2);
+        assert_eq!(config.sources[0].id().as_str(), "notes");
+        assert_eq!(config.sources[0].route(), Path::new("project/notes"));
+        assert_eq!(config.sources[0].walk_options().exclude, vec!["drafts/"]);
+        assert_eq!(config.sources[1].route(), Path::new("/srv/wiki"));
+        assert!(!config.sources[1].walk_options().recursive);
+        assert_eq!(config.sources[0].revision(), None);
+        assert_eq!(config.sources[1].revision(), Some("HEAD"));
+        assert_eq!(
+            config.outputs,
+            vec![Output {
+                format: Format::Html,
+                path: PathBuf::from("project/site"),
+                options: Options {
+                    cluster: false,
+                    templates_path: Some(PathBuf::from("project/templates")),
+                },
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_invalid_config() {
+        let unknown = parse(
+            "[[sources]]\nid = \"a\"\nroute = \".\"\nroot = \".\"\n",
+            Path::new("."),
+        );
+        let missing = parse("[[outputs]]\nformat = \"dot\"\n", Path::new("."));
+        let mistyped = parse("extractors = \"links\"\n", Path::new("."));
+
+        assert_eq!(
+            unknown.map_err(|err| err.to_string()),
+            Err("Unknown key `root` in sources".into())
+        );
+        assert_eq!(
+            missing.map_err(|err| err.to_string()),
+            Err("Missing key `path` in outputs".into())
+        );
+        assert_eq!(
+            mistyped.map_err(|err| err.to_string()),
+            Err("Expected `extractors` to be an array".into())
+        );
+    }
+
+    #[test]
+    fn discover_in_ancestors() -> Result<()> {
+        let dir = TempDir::new("config")?;
+        let nested = dir.join("a/b");
+        fs::create_dir_all(&nested)?;
+        fs::write(dir.join(FILENAME), "cache = \"x.db\"\n")?;
+
+        let found = discover(&nested);
+        let config = load(&dir.join(FILENAME))?;
+
+        assert_eq!(found, Some(dir.join(FILENAME)));
+        assert_eq!(config.cache_path, dir.join("x.db"));
+        assert_eq!(config.sources.len(), 1);
+        assert_eq!(config.sources[0].route(), &*dir);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! This module is concerned with the connections between nodes.
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// A directed connection found in the content of a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+    origin_id: String,
+    /// The target as written in the content, e.g. the href of a link.
+    target: String,
+    /// The text of the connection, e.g. the text of a link.
+    title: String,
+    connection_type: ConnectionType,
+    /// The byte span of the connection in the origin content.
+    span: Range<usize>,
+}
+
+impl Connection {
+    pub fn new(
+        origin_id: String,
+        target: String,
+        title: String,
+        connection_type: ConnectionType,
+        span: Range<usize>,
+    ) -> Self {
+        Connection {
+            origin_id,
+            target,
+            title,
+            connection_type,
+            span,
+        }
+    }
+
+    pub fn origin_id(&self) -> &str {
+        &self.origin_id
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn connection_type(&self) -> &ConnectionType {
+        &self.connection_type
+    }
+
+    pub fn span(&self) -> &Range<usize> {
+        &self.span
+    }
+}
+
+/// The kind of connection, for example how a link was written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionType {
+    /// A Markdown link like `[foo](bar)`.
+    Inline,
+    /// A Markdown link like `[foo][bar]`, `[foo][]` or `[foo]` with a matching definition.
+    Reference,
+    /// A Markdown link like `<http://foo.bar>` or `<foo@bar.baz>`.
+    Autolink,
+    /// A wikilink like `[[foo]]`, `[[foo|bar]]`, `[[foo#bar]]` or `[[foo^bar]]`.
+    Wikilink,
+}
+
+impl ConnectionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionType::Inline => "inline",
+            ConnectionType::Reference => "reference",
+            ConnectionType::Autolink => "autolink",
+            ConnectionType::Wikilink => "wikilink",
+        }
+    }
+}
+
+impl fmt::Display for ConnectionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ConnectionType {
+    type Err = ConnectionTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inline" => Ok(ConnectionType::Inline),
+            "reference" => Ok(ConnectionType::Reference),
+            "autolink" => Ok(ConnectionType::Autolink),
+            "wikilink" => Ok(ConnectionType::Wikilink),
+            _ => Err(ConnectionTypeError(s.into())),
+        }
+    }
+}
+
+/// An error which can be returned when parsing a connection type.
+#[derive(Debug)]
+pub struct ConnectionTypeError(String);
+
+impl fmt::Display for ConnectionTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown connection type `{}`", self.0)
+    }
+}
+
+impl Error for ConnectionTypeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_type_roundtrip() -> Result<(), ConnectionTypeError> {
+        for expected in &[
+            ConnectionType::Inline,
+            ConnectionType::Reference,
+            ConnectionType::Autolink,
+            ConnectionType::Wikilink,
+        ] {
+            let actual: ConnectionType = expected.as_str().parse()?;
+
+            assert_eq!(&actual, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_connection_type() {
+        let actual = ConnectionType::from_str("nope");
+
+        assert!(actual.is_err(), "Expected an unknown connection type");
+    }
+}
@@ -24,7 +24,7 @@ impl ContentType {
         use ContentTypeError::*;
 
         match ext {
-            "md" => Ok(Markdown),
+            "md" | "markdown" => Ok(Markdown),
             _ => Err(UnknownExtension(ext.into())),
         }
     }
@@ -44,6 +44,14 @@ impl ContentType {
             _ => Err(UnknownIana(s.into())),
         }
     }
+
+    /// Returns the IANA mime type for the content type.
+    pub fn as_iana(&self) -> &str {
+        match self {
+            ContentType::Markdown => "text/markdown",
+            ContentType::Other(s) => s,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -79,6 +87,15 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn parse_long_extension() -> Result<(), ContentTypeError> {
+        let actual = ContentType::from_extension("markdown")?;
+
+        assert_eq!(actual.as_iana(), "text/markdown");
+
+        Ok(())
+    }
+
     #[test]
     fn parse_unknown_extension() {
         let actual = ContentType::from_extension("oo");
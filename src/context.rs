@@ -39,6 +39,25 @@ impl Context {
             created: Utc::now(),
         }
     }
+
+    /// The commit hash Onelo was built from.
+    pub fn checksum(&self) -> &str {
+        &self.checksum
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn created(&self) -> &DateTime<Utc> {
+        &self.created
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
@@ -51,7 +70,7 @@ mod test {
             let context = Context::new();
 
             assert_eq!(context.version, VERSION);
-            assert!(context.created.to_string().len() > 0);
+            assert!(!context.created.to_string().is_empty());
         }
     }
 }
@@ -0,0 +1,394 @@
+//! This module is concerned with comparing the graphs built at two Git revisions.
+//!
+//! Nodes are matched by identifier. A node removed and a node added with the same content or the
+//! same front matter `id` in the same source are considered a rename, so connections from and to
+//! it are not reported as removed and added again. A rename needs a unique match on both sides and
+//! content shared by several nodes is not considered.
+
+use crate::cache;
+use crate::context::Result;
+use crate::extraction::Extractors;
+use crate::front_matter::Value;
+use crate::graph::{self, Graph, GraphNode};
+use crate::pipeline;
+use crate::source::Source;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+/// A resolved connection between two nodes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Link {
+    pub origin_id: String,
+    pub target_id: String,
+    pub connection_type: String,
+}
+
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {} ({})",
+            self.origin_id, self.target_id, self.connection_type
+        )
+    }
+}
+
+/// A front matter key whose value differs between revisions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataChange {
+    pub node_id: String,
+    pub key: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl fmt::Display for MetadataChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<Value>| match value {
+            Some(value) => serde_json::Value::from(value).to_string(),
+            None => "(none)".to_string(),
+        };
+
+        write!(
+            f,
+            "{} {}: {} -> {}",
+            self.node_id,
+            self.key,
+            show(&self.before),
+            show(&self.after)
+        )
+    }
+}
+
+/// The changes between two graphs.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Renamed nodes as pairs of previous and current identifier.
+    pub renamed: Vec<(String, String)>,
+    pub connections_added: Vec<Link>,
+    pub connections_removed: Vec<Link>,
+    pub metadata: Vec<MetadataChange>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self == &Diff::default()
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for id in &self.added {
+            writeln!(f, "+ node {}", id)?;
+        }
+
+        for id in &self.removed {
+            writeln!(f, "- node {}", id)?;
+        }
+
+        for (before, after) in &self.renamed {
+            writeln!(f, "~ node {} -> {}", before, after)?;
+        }
+
+        for link in &self.connections_added {
+            writeln!(f, "+ connection {}", link)?;
+        }
+
+        for link in &self.connections_removed {
+            writeln!(f, "- connection {}", link)?;
+        }
+
+        for change in &self.metadata {
+            writeln!(f, "~ metadata {}", change)?;
+        }
+
+        write!(
+            f,
+            "{} nodes added, {} removed, {} renamed. {} connections added, {} removed. {} metadata changes.",
+            self.added.len(),
+            self.removed.len(),
+            self.renamed.len(),
+            self.connections_added.len(),
+            self.connections_removed.len(),
+            self.metadata.len()
+        )
+    }
+}
+
+/// Builds the graph of the given sources read at each revision and compares them.
+pub fn run(sources: &[Source], extractors: &Extractors, before: &str, after: &str) -> Result<Diff> {
+    Ok(compare(
+        &build(sources, extractors, before)?,
+        &build(sources, extractors, after)?,
+    ))
+}
+
+/// Builds the graph of the given sources read at the given revision in a throwaway cache.
+fn build(sources: &[Source], extractors: &Extractors, revision: &str) -> Result<Graph> {
+    let mut conn = cache::connect(":memory:")?;
+    let sources: Vec<Source> = sources
+        .iter()
+        .map(|source| source.clone().with_revision(revision))
+        .collect();
+
+    pipeline::run(&mut conn, &sources, extractors)?;
+
+    graph::load(&conn)
+}
+
+/// Compares two graphs.
+pub fn compare(before: &Graph, after: &Graph) -> Diff {
+    let before_nodes: HashMap<&str, &GraphNode> =
+        before.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let after_nodes: HashMap<&str, &GraphNode> =
+        after.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let mut removed: Vec<&GraphNode> = before
+        .nodes
+        .iter()
+        .filter(|n| !after_nodes.contains_key(n.id.as_str()))
+        .collect();
+    let mut added: Vec<&GraphNode> = after
+        .nodes
+        .iter()
+        .filter(|n| !before_nodes.contains_key(n.id.as_str()))
+        .collect();
+    let mut diff = Diff::default();
+    let mut shared = shared_content(before);
+    shared.extend(shared_content(after));
+
+    for old in &removed {
+        let mut matches = added.iter().filter(|new| is_rename(old, new, &shared));
+
+        if let (Some(new), None) = (matches.next(), matches.next()) {
+            let rivals = removed
+                .iter()
+                .filter(|other| is_rename(other, new, &shared))
+                .count();
+
+            if rivals == 1 {
+                diff.renamed.push((old.id.clone(), new.id.clone()));
+            }
+        }
+    }
+
+    removed.retain(|old| !diff.renamed.iter().any(|(id, _)| *id == old.id));
+    added.retain(|new| !diff.renamed.iter().any(|(_, id)| *id == new.id));
+
+    diff.added = added.iter().map(|n| n.id.clone()).collect();
+    diff.removed = removed.iter().map(|n| n.id.clone()).collect();
+
+    // Identifiers before the change as they are known after it.
+    let renames: HashMap<&str, &str> = diff
+        .renamed
+        .iter()
+        .map(|(old, new)| (old.as_str(), new.as_str()))
+        .collect();
+    let current = |id: &str| renames.get(id).copied().unwrap_or(id).to_string();
+    // Links are counted so a node linking twice to another and then once shows a removal.
+    let links = |graph: &Graph, rename: &dyn Fn(&str) -> String| -> BTreeMap<Link, usize> {
+        let mut links = BTreeMap::new();
+
+        for edge in &graph.edges {
+            let link = Link {
+                origin_id: rename(&edge.origin_id),
+                target_id: rename(&edge.target_id),
+                connection_type: edge.connection_type.as_str().to_string(),
+            };
+
+            *links.entry(link).or_insert(0) += 1;
+        }
+
+        links
+    };
+    let before_links = links(before, &current);
+    let after_links = links(after, &|id: &str| id.to_string());
+
+    diff.connections_added = difference(&after_links, &before_links);
+    diff.connections_removed = difference(&before_links, &after_links);
+
+    for old in &before.nodes {
+        let new = match after_nodes.get(current(&old.id).as_str()) {
+            Some(new) => new,
+            None => continue,
+        };
+        let keys: BTreeSet<&String> = old.metadata.keys().chain(new.metadata.keys()).collect();
+
+        for key in keys {
+            let (before, after) = (old.metadata.get(key), new.metadata.get(key));
+
+            if before != after {
+                diff.metadata.push(MetadataChange {
+                    node_id: new.id.clone(),
+                    key: key.clone(),
+                    before: before.cloned(),
+                    after: after.cloned(),
+                });
+            }
+        }
+    }
+
+    diff
+}
+
+/// The links counted in `a` more times than in `b`, repeated as many times as the difference.
+fn difference(a: &BTreeMap<Link, usize>, b: &BTreeMap<Link, usize>) -> Vec<Link> {
+    let mut links = Vec::new();
+
+    for (link, count) in a {
+        let other = b.get(link).copied().unwrap_or(0);
+
+        for _ in other..*count {
+            links.push(link.clone());
+        }
+    }
+
+    links
+}
+
+/// The content identifiers shared by several nodes of a graph, e.g. empty files.
+fn shared_content(graph: &Graph) -> HashSet<&str> {
+    let mut seen = HashSet::new();
+
+    graph
+        .nodes
+        .iter()
+        .filter(|node| !seen.insert(node.content_id.as_str()))
+        .map(|node| node.content_id.as_str())
+        .collect()
+}
+
+/// Whether a removed node and an added node are the same node under a different path.
+///
+/// Nodes whose content is one of the given shared ones are only matched by front matter `id`.
+fn is_rename(old: &GraphNode, new: &GraphNode, shared: &HashSet<&str>) -> bool {
+    if old.source_id != new.source_id {
+        return false;
+    }
+
+    if old.content_id == new.content_id && !shared.contains(old.content_id.as_str()) {
+        return true;
+    }
+
+    match (old.metadata.get("id"), new.metadata.get("id")) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+    use crate::git::tests::commit;
+    use git2::Repository;
+
+    #[test]
+    fn diff_revisions() -> Result<()> {
+        let dir = TempDir::new("diff")?;
+        let repo = Repository::init(&dir)?;
+        let first = commit(
+            &repo,
+            &[
+                ("a.md", "+++\ntags = [\"x\"]\n+++\nSee [[b]] and [[c]]."),
+                ("b.md", "# B"),
+                ("c.md", "# C"),
+            ],
+            "First",
+        )?;
+        let mut index = repo.index()?;
+        index.remove_path(std::path::Path::new("b.md"))?;
+        index.remove_path(std::path::Path::new("c.md"))?;
+        index.write()?;
+        let second = commit(
+            &repo,
+            &[
+                ("a.md", "+++\ntags = [\"y\"]\n+++\nSee [[b]] and [[d]]."),
+                ("sub/b.md", "# B"),
+                ("d.md", "# D"),
+            ],
+            "Second",
+        )?;
+
+        let source = Source::new("notes".parse()?, &dir);
+        let diff = run(
+            &[source],
+            &Extractors::default(),
+            &first.to_string(),
+            &second.to_string(),
+        )?;
+
+        assert_eq!(diff.added, vec!["notes:d.md"]);
+        assert_eq!(diff.removed, vec!["notes:c.md"]);
+        assert_eq!(
+            diff.renamed,
+            vec![("notes:b.md".to_string(), "notes:sub/b.md".to_string())]
+        );
+        assert_eq!(
+            diff.connections_added,
+            vec![Link {
+                origin_id: "notes:a.md".into(),
+                target_id: "notes:d.md".into(),
+                connection_type: "wikilink".into(),
+            }]
+        );
+        assert_eq!(diff.connections_removed.len(), 1);
+        assert_eq!(diff.connections_removed[0].target_id, "notes:c.md");
+        assert_eq!(diff.metadata.len(), 1);
+        assert_eq!(
+            diff.metadata[0].to_string(),
+            r#"notes:a.md tags: ["x"] -> ["y"]"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_ambiguous_renames() -> Result<()> {
+        let dir = TempDir::new("diff-ambiguous")?;
+        let repo = Repository::init(&dir)?;
+        let first = commit(
+            &repo,
+            &[
+                ("a.md", "See [[c]]."),
+                ("c.md", "# C"),
+                ("x.md", "Same"),
+                ("y.md", "Same"),
+            ],
+            "First",
+        )?;
+        let mut index = repo.index()?;
+        index.remove_path(std::path::Path::new("x.md"))?;
+        index.remove_path(std::path::Path::new("y.md"))?;
+        index.write()?;
+        let second = commit(
+            &repo,
+            &[("a.md", "See [[c]] and [[c]]."), ("z.md", "Same")],
+            "Second",
+        )?;
+
+        let source = Source::new("notes".parse()?, &dir);
+        let diff = run(
+            &[source],
+            &Extractors::default(),
+            &first.to_string(),
+            &second.to_string(),
+        )?;
+
+        assert_eq!(diff.added, vec!["notes:z.md"]);
+        assert_eq!(diff.removed, vec!["notes:x.md", "notes:y.md"]);
+        assert!(diff.renamed.is_empty());
+        assert_eq!(
+            diff.connections_added,
+            vec![Link {
+                origin_id: "notes:a.md".into(),
+                target_id: "notes:c.md".into(),
+                connection_type: "wikilink".into(),
+            }]
+        );
+        assert!(diff.connections_removed.is_empty());
+
+        Ok(())
+    }
+}
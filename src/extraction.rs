@@ -0,0 +1,243 @@
+//! This module is concerned with the extraction phase.
+//!
+//! Extraction parses the source entries stored in the cache and generates the directed graph: a
+//! node per source entry and a connection per link or wikilink found in its content.
+//!
+//! Extraction is incremental: only source entries whose content differs from the one their node
+//! was extracted from are parsed again, unless the set of enabled extractors changed since the
+//! last run.
+
+use crate::cache::{self, StaleEntry};
+use crate::checksum::Checksum;
+use crate::connection;
+use crate::context::Result;
+use crate::front_matter;
+use crate::markdown;
+use crate::node::Node;
+use crate::source_entry::stem;
+use rusqlite::Connection;
+use std::fmt;
+
+/// A summary of the changes applied to the graph.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Report {
+    pub extracted: usize,
+    pub connections: usize,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes extracted with {} connections.",
+            self.extracted, self.connections
+        )
+    }
+}
+
+/// The extractors generating connections from the content of a source entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extractors {
+    /// Markdown inline, reference and autolink links.
+    pub links: bool,
+    pub wikilinks: bool,
+}
+
+impl Extractors {
+    /// Enables the extractors with the given names, `links` or `wikilinks`.
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Result<Self> {
+        let mut extractors = Extractors {
+            links: false,
+            wikilinks: false,
+        };
+
+        for name in names {
+            match name.as_ref() {
+                "links" => extractors.links = true,
+                "wikilinks" => extractors.wikilinks = true,
+                other => return Err(format!("Unknown extractor `{}`", other).into()),
+            }
+        }
+
+        Ok(extractors)
+    }
+
+    /// The names of the enabled extractors.
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+
+        if self.links {
+            names.push("links");
+        }
+
+        if self.wikilinks {
+            names.push("wikilinks");
+        }
+
+        names
+    }
+}
+
+impl Default for Extractors {
+    fn default() -> Self {
+        Extractors {
+            links: true,
+            wikilinks: true,
+        }
+    }
+}
+
+/// Extracts the nodes and connections of every stale source entry in the cache.
+pub fn run(conn: &Connection, extractors: &Extractors) -> Result<Report> {
+    let mut report = Report::default();
+    let names = extractors.names().join(",");
+
+    if cache::get_context_value(conn, "extractors")?.as_deref() != Some(names.as_str()) {
+        cache::invalidate_nodes(conn)?;
+        cache::insert_context_value(conn, "extractors", &names)?;
+    }
+
+    for StaleEntry {
+        id,
+        path,
+        content_id,
+    } in cache::get_stale_entries(conn)?
+    {
+        let blob = cache::get_content(conn, &content_id)?;
+        let text = std::str::from_utf8(&blob)?;
+        let document = front_matter::parse(text).map_err(|err| format!("{}:{}", id, err))?;
+
+        let title = document
+            .metadata
+            .get("title")
+            .and_then(|title| title.as_str())
+            .map(String::from)
+            .or_else(|| markdown::title(document.body))
+            .unwrap_or_else(|| stem(&path).to_string());
+        let node = Node::new(id.clone(), title, content_id.parse::<Checksum>()?);
+
+        cache::delete_connections(conn, &id)?;
+        cache::insert_node(conn, &node)?;
+
+        let mut links = Vec::new();
+
+        if extractors.links {
+            links.extend(markdown::links(document.body));
+        }
+
+        if extractors.wikilinks {
+            links.extend(markdown::wikilinks(document.body));
+        }
+
+        for link in links {
+            let start = document.body_offset + link.span.start;
+            let end = document.body_offset + link.span.end;
+            let connection = connection::Connection::new(
+                id.clone(),
+                link.href,
+                link.text,
+                link.connection_type,
+                start..end,
+            );
+
+            cache::insert_connection(conn, &connection)?;
+            report.connections += 1;
+        }
+
+        report.extracted += 1;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+    use crate::source::Source;
+    use crate::sourcing;
+    use rusqlite::{params, NO_PARAMS};
+    use std::fs;
+
+    #[test]
+    fn extract_links() -> Result<()> {
+        let dir = TempDir::new("extraction")?;
+        fs::write(
+            dir.join("a.md"),
+            "+++\ntitle = \"Alpha\"\n+++\nSee [b](b.md) and <https://example.org>.\n",
+        )?;
+        fs::write(dir.join("b.md"), "# Beta\n\nBack to [a][].\n\n[a]: a.md\n")?;
+        fs::write(dir.join("c.md"), "No links.\n")?;
+
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        let source = Source::new("test".parse()?, &dir);
+
+        sourcing::run(&conn, &source)?;
+        let first = run(&conn, &Extractors::default())?;
+
+        fs::remove_file(dir.join("c.md"))?;
+        sourcing::run(&conn, &source)?;
+        let second = run(&conn, &Extractors::default())?;
+
+        let nodes: i64 =
+            conn.query_row("SELECT count(*) FROM node", NO_PARAMS, |row| row.get(0))?;
+        let titles: Vec<String> = {
+            let mut stmt = conn.prepare("SELECT title FROM node ORDER BY id")?;
+            let rows = stmt.query_map(NO_PARAMS, |row| row.get(0))?;
+            rows.collect::<std::result::Result<_, _>>()?
+        };
+        let (target, span_start, span_end): (String, i64, i64) = conn.query_row(
+            "SELECT target, span_start, span_end FROM connection WHERE origin_id = ?1 AND connection_type_id = 'inline'",
+            params!["test:a.md"],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let content = fs::read_to_string(dir.join("a.md"))?;
+        let third = run(&conn, &Extractors::from_names(&["wikilinks"])?)?;
+
+        assert_eq!(
+            first,
+            Report {
+                extracted: 3,
+                connections: 3,
+            }
+        );
+        assert_eq!(second, Report::default());
+        assert_eq!(
+            third,
+            Report {
+                extracted: 2,
+                connections: 0,
+            }
+        );
+        assert_eq!(nodes, 2);
+        assert_eq!(titles, vec!["Alpha", "Beta"]);
+        assert_eq!(target, "b.md");
+        assert_eq!(
+            &content[span_start as usize..span_end as usize],
+            "[b](b.md)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn extract_connections_sharing_a_start() -> Result<()> {
+        let dir = TempDir::new("extraction-span")?;
+        fs::write(dir.join("a.md"), "[[b]](c.md)\n")?;
+
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        sourcing::run(&conn, &Source::new("test".parse()?, &dir))?;
+        let report = run(&conn, &Extractors::default())?;
+
+        let stored: i64 = conn.query_row("SELECT count(*) FROM connection", NO_PARAMS, |row| {
+            row.get(0)
+        })?;
+
+        assert_eq!(report.connections, 2);
+        assert_eq!(stored, 2);
+
+        Ok(())
+    }
+}
@@ -1,75 +1,177 @@
 //! This module deals with the collection of information from files.
 //!
-//! In early versions only markdown files are allowed
+//! Only Markdown files are walked unless told otherwise.
 
+use crate::artefact::Content;
+use crate::checksum::Checksum;
 use crate::context::Result;
-// use regex::Regex;
-use std::error::Error;
-use std::fmt;
+use chrono::prelude::*;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
-struct SplitError(String);
+/// The patterns included when none are given.
+pub const DEFAULT_INCLUDE: [&str; 2] = ["*.md", "*.markdown"];
+
+/// The name of the file listing paths to leave out of a source, with the `.gitignore` syntax.
+pub const IGNORE_FILENAME: &str = ".oneloignore";
+
+/// The options deciding which files of a source are walked.
+///
+/// Globs match paths relative to the source root using `/` as separator, where `*` also matches
+/// `/`. As in `.gitignore` files, a glob without a `/` other than a trailing one matches at any
+/// depth, e.g. `drafts/` matches `notes/drafts`, while `notes/drafts/` only matches that
+/// directory. A directory matching an exclude glob is not walked at all.
+///
+/// Unless `ignore` is off, files ignored by `.oneloignore` files, and by `.gitignore` files,
+/// `.git/info/exclude` and the global Git excludes when the source is in a Git working copy, are
+/// not walked either.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkOptions {
+    pub recursive: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub ignore: bool,
+}
 
-impl fmt::Display for SplitError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            recursive: true,
+            include: DEFAULT_INCLUDE.iter().map(|s| s.to_string()).collect(),
+            exclude: Vec::new(),
+            ignore: true,
+        }
     }
 }
 
-impl Error for SplitError {}
+/// Get all files from a path matching the given options, sorted.
+pub fn get_files<P: AsRef<Path>>(path: P, options: &WalkOptions) -> Result<Vec<PathBuf>> {
+    let root = path.as_ref().to_path_buf();
+    let include = glob_set(&options.include)?;
+    let exclude = glob_set(&options.exclude)?;
+    let mut builder = WalkBuilder::new(&root);
+
+    builder
+        .standard_filters(false)
+        .git_ignore(options.ignore)
+        .git_exclude(options.ignore)
+        .git_global(options.ignore)
+        .parents(options.ignore)
+        .max_depth(if options.recursive { None } else { Some(1) })
+        .filter_entry({
+            let root = root.clone();
+
+            move |entry| {
+                entry.file_name() != ".git"
+                    && !relative_path(entry.path(), &root)
+                        .map(|relative| exclude.is_match(relative))
+                        .unwrap_or(false)
+            }
+        });
+
+    if options.ignore {
+        builder.add_custom_ignore_filename(IGNORE_FILENAME);
+    }
 
-/// Get all valid files from a path
-pub fn get_files<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
     let mut paths = vec![];
 
-    for result in fs::read_dir(path)? {
-        let path = result?.path();
+    for result in builder.build() {
+        let entry = result?;
+        let is_file = entry.file_type().is_some_and(|t| t.is_file());
 
-        if path.is_file() {
-            if is_valid_file(&path) {
-                paths.push(path);
-            }
+        if is_file && include.is_match(relative_path(entry.path(), &root)?) {
+            paths.push(entry.into_path());
         }
     }
 
+    paths.sort();
+
     Ok(paths)
 }
 
-/// Get all valid files from a path and all subdirs
-pub fn get_files_recursive<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
-    let mut paths = vec![];
+/// Compiles the given glob patterns, ignoring trailing slashes and matching patterns without a
+/// `/` at any depth.
+pub fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
 
-    for result in fs::read_dir(path)? {
-        let path = result?.path();
+    for pattern in patterns {
+        let pattern = pattern.trim_end_matches('/');
 
-        if path.is_dir() {
-            let recursive_paths = get_files_recursive(&path);
-            paths.append(recursive_paths?.as_mut());
+        if pattern.contains('/') {
+            builder.add(Glob::new(pattern)?);
+        } else {
+            builder.add(Glob::new(&format!("**/{}", pattern))?);
         }
+    }
 
-        if path.is_file() {
-            if is_valid_file(&path) {
-                paths.push(path);              
-            }
+    Ok(builder.build()?)
+}
+
+/// The ignore files found in a source not walked from the filesystem, e.g. a Git commit or an
+/// archive.
+#[derive(Debug, Default)]
+pub struct IgnoreFiles(Vec<(String, Gitignore)>);
+
+impl IgnoreFiles {
+    /// Adds the content of an ignore file found in the given directory, relative to the source
+    /// root and ending with `/` unless it is the root itself.
+    pub fn add(&mut self, dir: &str, content: &[u8]) -> Result<()> {
+        let mut builder = GitignoreBuilder::new("");
+
+        for line in String::from_utf8_lossy(content).lines() {
+            builder.add_line(None, line)?;
         }
+
+        self.0.push((dir.to_string(), builder.build()?));
+
+        Ok(())
     }
 
-    Ok(paths)
+    /// Whether a path relative to the source root is ignored by any of the files.
+    pub fn is_ignored(&self, path: &str) -> bool {
+        self.0.iter().any(|(dir, matcher)| {
+            path.strip_prefix(dir.as_str()).is_some_and(|relative| {
+                matcher
+                    .matched_path_or_any_parents(relative, false)
+                    .is_ignore()
+            })
+        })
+    }
 }
 
-/// Check if a file is a valid source
-fn is_valid_file(path: &PathBuf) -> bool {
-    // TODO: This array should go in other place, like context...
-    let valid_extensions = ["md", "markdown"];
+/// Computes the path of a file relative to the given root using `/` as separator regardless of
+/// the platform.
+pub fn relative_path(path: &Path, root: &Path) -> Result<String> {
+    let parts: Vec<String> = path
+        .strip_prefix(root)?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
 
-    if let Some(ext) = path.extension() {
-        let extension = ext.to_str().unwrap();
-        return valid_extensions.contains(&extension);
-    }
+    Ok(parts.join("/"))
+}
 
-    false
+/// The file metadata used to detect whether a file changed between builds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stamp {
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+    /// A hash of the content known without reading it, e.g. the Git blob id.
+    pub digest: Option<String>,
+}
+
+/// Get the size and modification time of a file
+pub fn get_stamp<P: AsRef<Path>>(path: P) -> Result<Stamp> {
+    let metadata = fs::metadata(path)?;
+
+    Ok(Stamp {
+        size: metadata.len(),
+        modified: metadata.modified()?.into(),
+        digest: None,
+    })
 }
 
 /// Get the content of a file as String
@@ -79,6 +181,15 @@ pub fn get_content_as_string<P: AsRef<Path>>(path: P) -> Result<String> {
     Ok(file_content)
 }
 
+/// Get the content of a file, hashing it as it is read.
+pub fn get_content<P: AsRef<Path>>(path: P) -> Result<Content> {
+    let file = fs::File::open(path)?;
+    let mut blob = Vec::with_capacity(file.metadata()?.len() as usize);
+    let checksum = Checksum::from_reader(file, &mut blob)?;
+
+    Ok(Content::new(checksum, blob))
+}
+
 /// Get the content of a file as binary
 pub fn get_content_as_binary<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
     let file_content = fs::read(path)?;
@@ -86,51 +197,167 @@ pub fn get_content_as_binary<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
     Ok(file_content)
 }
 
-/// Take an String with all the file content and split it in metada and data
-// TODO: Move this function out of this module. Split the content should be in charge of another module
-// pub fn split_content<'c>(content: &'c str) -> Result<(&'c str, &'c str)> {
-//     let split_regex =
-//         Regex::new(r"^[[:space:]]*\+\+\+(\r?\n(?s).*?(?-s))\+\+\+\r?\n?((?s).*(?-s))$")
-//             .expect("Something went wrong when compiling a regular expression.");
-
-//     if !split_regex.is_match(content) {
-//         return Err(Box::new(SplitError(
-//             "Couldn't find metadata. Did you forget to add `+++`?".into(),
-//         )));
-//     }
-
-//     let captures = split_regex.captures(content).ok_or(SplitError(
-//         "Something went wrong when splitting the content.".into(),
-//     ))?;
-
-//     Ok((
-//         captures
-//             .get(1)
-//             .ok_or(SplitError("Couldn't find any metadata".into()))?
-//             .as_str()
-//             .trim(),
-//         captures
-//             .get(2)
-//             .ok_or(SplitError("Couldn't find any content".into()))?
-//             .as_str()
-//             .trim(),
-//     ))
-// }
-
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use std::env;
+    use std::ops::Deref;
+    use std::path::PathBuf;
+
+    /// A directory under the system temporary directory, removed when dropped so a failing test
+    /// doesn't leave it behind.
+    #[derive(Debug)]
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        /// Creates an empty directory named after the given test.
+        pub(crate) fn new(name: &str) -> Result<Self> {
+            let path = env::temp_dir().join(format!("onelo-{}-{}", name, std::process::id()));
+
+            if path.exists() {
+                fs::remove_dir_all(&path)?;
+            }
+
+            fs::create_dir_all(&path)?;
+
+            Ok(TempDir(path))
+        }
+    }
+
+    impl Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn walk(options: &WalkOptions) -> Result<Vec<String>> {
+        get_files("test/files", options)?
+            .iter()
+            .map(|path| relative_path(path, Path::new("test/files")))
+            .collect()
+    }
 
     #[test]
-    fn get_valid_files() {
-        let paths = get_files("test/files");
-        assert_eq!(paths.unwrap().len(), 2);
+    fn get_valid_files() -> Result<()> {
+        let options = WalkOptions {
+            recursive: false,
+            ..WalkOptions::default()
+        };
+
+        assert_eq!(walk(&options)?, vec!["01.md", "02.md"]);
+
+        Ok(())
     }
 
     #[test]
-    fn get_valid_files_recursive() {
-        let paths = get_files_recursive("test/files");
-        assert_eq!(paths.unwrap().len(), 4);
+    fn get_valid_files_recursive() -> Result<()> {
+        assert_eq!(
+            walk(&WalkOptions::default())?,
+            vec!["01.md", "02.md", "subdir/a.md", "subdir/c.md"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_files_with_globs() -> Result<()> {
+        let excluded = WalkOptions {
+            exclude: vec!["subdir/".to_string(), "02.*".to_string()],
+            ..WalkOptions::default()
+        };
+        let included = WalkOptions {
+            include: vec!["*.txt".to_string()],
+            ..WalkOptions::default()
+        };
+
+        assert_eq!(walk(&excluded)?, vec!["01.md"]);
+        assert_eq!(walk(&included)?, vec!["01.txt", "subdir/b.txt"]);
+
+        let dir = TempDir::new("globs")?;
+        fs::create_dir_all(dir.join("drafts"))?;
+        fs::create_dir_all(dir.join("notes/drafts"))?;
+        fs::write(dir.join("drafts/a.md"), "")?;
+        fs::write(dir.join("notes/b.md"), "")?;
+        fs::write(dir.join("notes/drafts/c.md"), "")?;
+
+        let names = |exclude: &str| -> Result<Vec<String>> {
+            let options = WalkOptions {
+                exclude: vec![exclude.to_string()],
+                ..WalkOptions::default()
+            };
+
+            get_files(&dir, &options)?
+                .iter()
+                .map(|path| relative_path(path, &dir))
+                .collect()
+        };
+
+        assert_eq!(names("drafts/")?, vec!["notes/b.md"]);
+        assert_eq!(names("notes/drafts/")?, vec!["drafts/a.md", "notes/b.md"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_files_not_ignored() -> Result<()> {
+        let dir = TempDir::new("ignore")?;
+        fs::create_dir_all(dir.join(".git"))?;
+        fs::create_dir_all(dir.join("private"))?;
+        fs::write(dir.join(".gitignore"), "private/\n")?;
+        fs::write(dir.join(IGNORE_FILENAME), "draft-*.md\n")?;
+        fs::write(dir.join("a.md"), "")?;
+        fs::write(dir.join("draft-b.md"), "")?;
+        fs::write(dir.join("private/c.md"), "")?;
+        fs::write(dir.join(".git/d.md"), "")?;
+
+        let names = |options: &WalkOptions| -> Result<Vec<String>> {
+            get_files(&dir, options)?
+                .iter()
+                .map(|path| relative_path(path, &dir))
+                .collect()
+        };
+        let ignored = names(&WalkOptions::default())?;
+        let all = names(&WalkOptions {
+            ignore: false,
+            ..WalkOptions::default()
+        })?;
+
+        assert_eq!(ignored, vec!["a.md"]);
+        assert_eq!(all, vec!["a.md", "draft-b.md", "private/c.md"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn relative_path_uses_slashes() -> Result<()> {
+        let actual = relative_path(Path::new("test/files/subdir/a.md"), Path::new("test/files"))?;
+
+        assert_eq!(actual, "subdir/a.md");
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_file_stamp() -> Result<()> {
+        let stamp = get_stamp("test/files/02.md")?;
+
+        assert_eq!(stamp.size, 0);
+
+        Ok(())
     }
 
     #[test]
@@ -153,48 +380,4 @@ Lorem ipsum dolor sit amet, consectetur adipiscing elit. Nunc posuere nibh eget
         let file_content = get_content_as_binary("test/files/01.md");
         assert!(file_content.is_ok());
     }
-
-    //     #[test]
-    //     fn split_content_with_metadata() {
-    //         let content = r#"+++
-    // [metadata]
-    // id = "01fbd72a-5ad4-4d4d-bc6e-7973e65e02b6"
-    // +++
-
-    // # Lorem ipsum
-
-    // Lorem ipsum dolor sit amet, consectetur adipiscing elit. Nunc posuere nibh eget tortor rhoncus dictum. Lorem ipsum dolor sit amet, consectetur adipiscing elit."#;
-    //         let content_split = split_content(content).unwrap();
-
-    //         assert_eq!(
-    //             "[metadata]\nid = \"01fbd72a-5ad4-4d4d-bc6e-7973e65e02b6\"",
-    //             content_split.0
-    //         );
-
-    //         assert_eq!(
-    //             "# Lorem ipsum\n\nLorem ipsum dolor sit amet, consectetur adipiscing elit. Nunc posuere nibh eget tortor rhoncus dictum. Lorem ipsum dolor sit amet, consectetur adipiscing elit.",
-    //             content_split.1
-    //         );
-    //     }
-
-    //     #[test]
-    //     fn split_content_with_empty_metadata() {
-    //         let content = r#"+++
-    // +++
-
-    // # Lorem ipsum
-
-    // Lorem ipsum dolor sit amet, consectetur adipiscing elit. Nunc posuere nibh eget tortor rhoncus dictum. Lorem ipsum dolor sit amet, consectetur adipiscing elit."#;
-    //         assert_eq!(split_content(content).is_ok(), true);
-    //     }
-
-    //     #[test]
-    //     fn split_content_without_metadata() {
-    //         let content = r#"
-    // # Lorem ipsum
-
-    // Lorem ipsum dolor sit amet, consectetur adipiscing elit. Nunc posuere nibh eget tortor rhoncus dictum. Lorem ipsum dolor sit amet, consectetur adipiscing elit."#;
-
-    //         assert_eq!(split_content(content).is_err(), true);
-    //     }
 }
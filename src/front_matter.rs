@@ -0,0 +1,699 @@
+//! This module is concerned with the metadata found at the top of a piece of content.
+//!
+//! The delimiter of a front matter determines its format:
+//!
+//! * TOML blocks are delimited by `+++` lines.
+//! * YAML blocks are delimited by `---` lines.
+//! * JSON blocks are either delimited by `;;;` lines or a leading object ending a line, e.g. a
+//!   `{` line up to a `}` line or a single `{"id": "01"}` line. Any other content starting with
+//!   `{`, such as a `{% include %}` tag, has no front matter.
+//!
+//! ```text
+//! +++
+//! id = "01"
+//! +++
+//!
+//! # Lorem ipsum
+//! ```
+//!
+//! All formats are normalised into the same `Metadata` representation.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The metadata of a piece of content.
+pub type Metadata = BTreeMap<String, Value>;
+
+/// The format of a front matter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl Format {
+    /// The line opening a front matter block.
+    fn opening(&self) -> &'static str {
+        match self {
+            Format::Toml => "+++",
+            Format::Yaml => "---",
+            Format::Json => ";;;",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Toml => "toml",
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Format {
+    type Err = FrontMatterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "toml" => Ok(Format::Toml),
+            "yaml" => Ok(Format::Yaml),
+            "json" => Ok(Format::Json),
+            _ => Err(FrontMatterError::UnknownFormat(s.into())),
+        }
+    }
+}
+
+/// A metadata value.
+///
+/// Datetimes are kept in their original textual form.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Datetime(String),
+    Array(Vec<Value>),
+    Table(Metadata),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Casts scalar values usable as identifiers, such as `"01"` or `1`, as a string.
+    pub fn to_key(&self) -> Option<String> {
+        match self {
+            Value::String(s) => Some(s.clone()),
+            Value::Integer(i) => Some(i.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl From<toml::Value> for Value {
+    fn from(value: toml::Value) -> Self {
+        match value {
+            toml::Value::String(s) => Value::String(s),
+            toml::Value::Integer(i) => Value::Integer(i),
+            toml::Value::Float(f) => Value::Float(f),
+            toml::Value::Boolean(b) => Value::Boolean(b),
+            toml::Value::Datetime(d) => Value::Datetime(d.to_string()),
+            toml::Value::Array(a) => Value::Array(a.into_iter().map(Value::from).collect()),
+            toml::Value::Table(t) => {
+                Value::Table(t.into_iter().map(|(k, v)| (k, v.into())).collect())
+            }
+        }
+    }
+}
+
+impl From<serde_yaml::Value> for Value {
+    fn from(value: serde_yaml::Value) -> Self {
+        use serde_yaml::Value as Yaml;
+
+        match value {
+            Yaml::Null => Value::Null,
+            Yaml::Bool(b) => Value::Boolean(b),
+            Yaml::Number(n) => match n.as_i64() {
+                Some(i) => Value::Integer(i),
+                None => Value::Float(n.as_f64().unwrap_or_default()),
+            },
+            Yaml::String(s) => Value::String(s),
+            Yaml::Sequence(a) => Value::Array(a.into_iter().map(Value::from).collect()),
+            Yaml::Mapping(m) => Value::Table(
+                m.into_iter()
+                    .map(|(k, v)| (yaml_key(k), v.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Casts a YAML mapping key as a string. YAML allows any value as a key, metadata doesn't.
+fn yaml_key(key: serde_yaml::Value) -> String {
+    use serde_yaml::Value as Yaml;
+
+    match key {
+        Yaml::String(s) => s,
+        Yaml::Bool(b) => b.to_string(),
+        Yaml::Number(n) => n.to_string(),
+        Yaml::Null => String::new(),
+        other => serde_yaml::to_string(&other)
+            .map(|s| s.trim_start_matches("---").trim().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+impl From<&Value> for serde_json::Value {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => serde_json::Value::Null,
+            Value::String(s) => s.as_str().into(),
+            Value::Integer(i) => (*i).into(),
+            Value::Float(f) => (*f).into(),
+            Value::Boolean(b) => (*b).into(),
+            Value::Datetime(d) => d.as_str().into(),
+            Value::Array(a) => a.iter().map(serde_json::Value::from).collect(),
+            Value::Table(t) => {
+                serde_json::Value::Object(t.iter().map(|(k, v)| (k.clone(), v.into())).collect())
+            }
+        }
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        use serde_json::Value as Json;
+
+        match value {
+            Json::Null => Value::Null,
+            Json::Bool(b) => Value::Boolean(b),
+            Json::Number(n) => match n.as_i64() {
+                Some(i) => Value::Integer(i),
+                None => Value::Float(n.as_f64().unwrap_or_default()),
+            },
+            Json::String(s) => Value::String(s),
+            Json::Array(a) => Value::Array(a.into_iter().map(Value::from).collect()),
+            Json::Object(o) => Value::Table(o.into_iter().map(|(k, v)| (k, v.into())).collect()),
+        }
+    }
+}
+
+/// The key of the single-entry object standing for a datetime in the cache form, given that
+/// JSON has no datetime type.
+const DATETIME_TAG: &str = "$datetime";
+
+/// Serialises the metadata as a JSON object, the form used to store it in the cache.
+///
+/// Unlike the plain JSON conversion, datetimes are tagged so they survive `from_json`.
+pub fn to_json(metadata: &Metadata) -> String {
+    let object: serde_json::Map<String, serde_json::Value> =
+        metadata.iter().map(|(k, v)| (k.clone(), tag(v))).collect();
+
+    serde_json::Value::Object(object).to_string()
+}
+
+/// Deserialises the metadata from a JSON object as stored in the cache.
+pub fn from_json(s: &str) -> Result<Metadata, serde_json::Error> {
+    let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(s)?;
+
+    Ok(object.into_iter().map(|(k, v)| (k, untag(v))).collect())
+}
+
+fn tag(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Datetime(d) => serde_json::json!({ DATETIME_TAG: d }),
+        Value::Array(a) => a.iter().map(tag).collect(),
+        Value::Table(t) => {
+            serde_json::Value::Object(t.iter().map(|(k, v)| (k.clone(), tag(v))).collect())
+        }
+        other => other.into(),
+    }
+}
+
+fn untag(value: serde_json::Value) -> Value {
+    use serde_json::Value as Json;
+
+    match value {
+        Json::Object(o) if o.len() == 1 && o.contains_key(DATETIME_TAG) => match &o[DATETIME_TAG] {
+            Json::String(d) => Value::Datetime(d.clone()),
+            _ => Value::Table(o.into_iter().map(|(k, v)| (k, untag(v))).collect()),
+        },
+        Json::Array(a) => Value::Array(a.into_iter().map(untag).collect()),
+        Json::Object(o) => Value::Table(o.into_iter().map(|(k, v)| (k, untag(v))).collect()),
+        other => other.into(),
+    }
+}
+
+/// A piece of content split in its front matter and its body.
+#[derive(Debug, PartialEq)]
+pub struct Split<'c> {
+    /// The raw front matter without delimiters, if any.
+    pub front_matter: Option<&'c str>,
+    pub format: Option<Format>,
+    /// The line number (1-based) where the raw front matter starts.
+    pub line: usize,
+    pub body: &'c str,
+    /// The byte offset of the body in the original content.
+    pub body_offset: usize,
+}
+
+/// A piece of content with its front matter parsed.
+#[derive(Debug, PartialEq)]
+pub struct Document<'c> {
+    pub metadata: Metadata,
+    /// The format of the front matter, if any.
+    pub format: Option<Format>,
+    pub body: &'c str,
+    /// The byte offset of the body in the original content.
+    pub body_offset: usize,
+}
+
+/// Splits the front matter from the body.
+///
+/// Content without a front matter is considered to be all body.
+///
+/// ## Errors
+///
+/// Returns a `FrontMatterError::Unclosed` if the opening delimiter has no closing counterpart.
+pub fn split(content: &str) -> Result<Split<'_>, FrontMatterError> {
+    let mut lines = content.split_inclusive('\n');
+    let no_front_matter = Split {
+        front_matter: None,
+        format: None,
+        line: 0,
+        body: content,
+        body_offset: 0,
+    };
+
+    let opening = match lines.next() {
+        Some(line) => line,
+        None => return Ok(no_front_matter),
+    };
+    let opening_trimmed = opening.trim_end();
+    let format = [Format::Toml, Format::Yaml, Format::Json]
+        .iter()
+        .find(|format| format.opening() == opening_trimmed)
+        .copied();
+
+    let (format, closing) = match format {
+        Some(format) => (format, format.opening()),
+        None if opening_trimmed.starts_with('{') => {
+            return Ok(split_json(content).unwrap_or(no_front_matter))
+        }
+        None => return Ok(no_front_matter),
+    };
+    let mut offset = opening.len();
+
+    for current in lines {
+        if current.trim_end() == closing {
+            let body_offset = offset + current.len();
+
+            return Ok(Split {
+                front_matter: Some(&content[opening.len()..offset]),
+                format: Some(format),
+                line: 2,
+                body: &content[body_offset..],
+                body_offset,
+            });
+        }
+
+        offset += current.len();
+    }
+
+    Err(FrontMatterError::Unclosed {
+        line: 1,
+        delimiter: closing,
+    })
+}
+
+/// Splits a leading JSON object, which is its own delimiter so the braces are kept.
+///
+/// A well-formed object must end its line. A malformed one is only taken as a front matter when
+/// it opens with a `{` line and has a closing `}` line, so its errors can be reported.
+fn split_json(content: &str) -> Option<Split<'_>> {
+    let mut values = serde_json::Deserializer::from_str(content).into_iter::<serde_json::Value>();
+    let end = match values.next() {
+        Some(Ok(serde_json::Value::Object(_))) => values.byte_offset(),
+        Some(Err(_)) if content.lines().next()?.trim_end() == "{" => {
+            let mut offset = 0;
+
+            content.split_inclusive('\n').find_map(|line| {
+                let start = offset;
+                offset += line.len();
+
+                // The end of the object is right after the closing brace.
+                (start > 0 && line.trim_end() == "}").then(|| start + 1)
+            })?
+        }
+        _ => return None,
+    };
+    let rest = &content[end..];
+    let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+
+    if !rest[..line_end].trim().is_empty() {
+        return None;
+    }
+
+    let body_offset = end + line_end;
+
+    Some(Split {
+        front_matter: Some(&content[..end]),
+        format: Some(Format::Json),
+        line: 1,
+        body: &content[body_offset..],
+        body_offset,
+    })
+}
+
+/// Finds the line and column (1-based) where the given top level key is defined in the front
+/// matter, if any.
+pub fn key_position(content: &str, key: &str) -> Option<(usize, usize)> {
+    let split = split(content).ok()?;
+    let raw = split.front_matter?;
+    let quoted = format!("\"{}\"", key);
+
+    raw.lines().enumerate().find_map(|(i, line)| {
+        let trimmed = line.trim_start();
+        let rest = trimmed
+            .strip_prefix(quoted.as_str())
+            .or_else(|| trimmed.strip_prefix(key))?;
+
+        if rest.trim_start().starts_with(['=', ':']) {
+            Some((split.line + i, line.len() - trimmed.len() + 1))
+        } else {
+            None
+        }
+    })
+}
+
+/// Splits the front matter from the body and parses it according to its format.
+///
+/// ## Errors
+///
+/// Returns a `FrontMatterError` with the line and column, relative to the whole content, where
+/// the front matter is malformed.
+pub fn parse(content: &str) -> Result<Document<'_>, FrontMatterError> {
+    let split = split(content)?;
+    let metadata = match (split.front_matter, split.format) {
+        (Some(raw), Some(Format::Toml)) => parse_toml(raw, split.line)?,
+        (Some(raw), Some(Format::Yaml)) => parse_yaml(raw, split.line)?,
+        (Some(raw), Some(Format::Json)) => parse_json(raw, split.line)?,
+        _ => Metadata::new(),
+    };
+
+    Ok(Document {
+        metadata,
+        format: split.format,
+        body: split.body,
+        body_offset: split.body_offset,
+    })
+}
+
+fn parse_toml(raw: &str, line: usize) -> Result<Metadata, FrontMatterError> {
+    let table: toml::value::Table = toml::from_str(raw).map_err(|err| {
+        // `toml` positions are 0-based.
+        let (l, c) = err.line_col().unwrap_or((0, 0));
+
+        FrontMatterError::syntax(line + l, c + 1, err.to_string())
+    })?;
+
+    Ok(table.into_iter().map(|(k, v)| (k, v.into())).collect())
+}
+
+fn parse_yaml(raw: &str, line: usize) -> Result<Metadata, FrontMatterError> {
+    let value: serde_yaml::Value = serde_yaml::from_str(raw).map_err(|err| {
+        let (l, c) = err
+            .location()
+            .map(|loc| (loc.line(), loc.column()))
+            .unwrap_or((1, 1));
+
+        FrontMatterError::syntax(line + l - 1, c, err.to_string())
+    })?;
+
+    match value.into() {
+        Value::Null => Ok(Metadata::new()),
+        Value::Table(metadata) => Ok(metadata),
+        _ => Err(FrontMatterError::syntax(
+            line,
+            1,
+            "A YAML front matter must be a mapping".into(),
+        )),
+    }
+}
+
+fn parse_json(raw: &str, line: usize) -> Result<Metadata, FrontMatterError> {
+    let value: serde_json::Value = serde_json::from_str(raw).map_err(|err| {
+        FrontMatterError::syntax(line + err.line() - 1, err.column(), err.to_string())
+    })?;
+
+    match value.into() {
+        Value::Table(metadata) => Ok(metadata),
+        _ => Err(FrontMatterError::syntax(
+            line,
+            1,
+            "A JSON front matter must be an object".into(),
+        )),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrontMatterError {
+    Unclosed {
+        line: usize,
+        delimiter: &'static str,
+    },
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    UnknownFormat(String),
+}
+
+impl FrontMatterError {
+    /// Builds a syntax error dropping any position from the parser message given that parsers
+    /// only know about positions relative to the front matter, not the whole content.
+    fn syntax(line: usize, column: usize, message: String) -> Self {
+        let message = match message.rfind(" at line ") {
+            Some(i) => message[..i].to_string(),
+            None => message,
+        };
+
+        FrontMatterError::Syntax {
+            line,
+            column,
+            message,
+        }
+    }
+
+    /// The line and column where the error was found.
+    pub fn position(&self) -> (usize, usize) {
+        match self {
+            FrontMatterError::Unclosed { line, .. } => (*line, 1),
+            FrontMatterError::Syntax { line, column, .. } => (*line, *column),
+            FrontMatterError::UnknownFormat(_) => (0, 0),
+        }
+    }
+
+    /// The description of the error, without its position.
+    pub fn message(&self) -> String {
+        match self {
+            FrontMatterError::Unclosed { delimiter, .. } => {
+                format!("The front matter is missing its closing `{}`", delimiter)
+            }
+            FrontMatterError::Syntax { message, .. } => message.clone(),
+            FrontMatterError::UnknownFormat(s) => format!("Unknown front matter format `{}`", s),
+        }
+    }
+}
+
+impl fmt::Display for FrontMatterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position() {
+            (0, 0) => write!(f, "{}", self.message()),
+            (line, column) => write!(f, "{}:{}: {}", line, column, self.message()),
+        }
+    }
+}
+
+impl Error for FrontMatterError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_with_front_matter() -> Result<(), FrontMatterError> {
+        let content = "+++\nid = \"01\"\n+++\n\n# Lorem ipsum\n";
+        let actual = split(content)?;
+
+        assert_eq!(actual.front_matter, Some("id = \"01\"\n"));
+        assert_eq!(actual.body, "\n# Lorem ipsum\n");
+        assert_eq!(&content[actual.body_offset..], actual.body);
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_with_empty_front_matter() -> Result<(), FrontMatterError> {
+        let actual = split("+++\r\n+++\r\n# Lorem ipsum")?;
+
+        assert_eq!(actual.front_matter, Some(""));
+        assert_eq!(actual.body, "# Lorem ipsum");
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_without_front_matter() -> Result<(), FrontMatterError> {
+        let content = "# Lorem ipsum\n+++\n";
+        let actual = split(content)?;
+
+        assert_eq!(actual.front_matter, None);
+        assert_eq!(actual.body, content);
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_unclosed() {
+        let actual = split("+++\nid = \"01\"\n# Lorem ipsum");
+
+        assert_eq!(
+            actual,
+            Err(FrontMatterError::Unclosed {
+                line: 1,
+                delimiter: "+++"
+            })
+        );
+    }
+
+    #[test]
+    fn parse_metadata() -> Result<(), FrontMatterError> {
+        let content = "+++\nid = \"01\"\ntags = [\"a\", \"b\"]\ndraft = true\n+++\nbody";
+        let actual = parse(content)?;
+
+        assert_eq!(actual.metadata["id"], Value::String("01".into()));
+        assert_eq!(
+            actual.metadata["tags"],
+            Value::Array(vec![Value::String("a".into()), Value::String("b".into())])
+        );
+        assert_eq!(actual.metadata["draft"], Value::Boolean(true));
+        assert_eq!(actual.body, "body");
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_reports_line() {
+        let content = "+++\nid = \"01\"\ntitle = \n+++\nbody";
+        let actual = parse(content).map(|_| ()).unwrap_err();
+
+        assert_eq!(actual.position().0, 3);
+        assert!(!actual.to_string().contains("at line"));
+    }
+
+    #[test]
+    fn parse_yaml_metadata() -> Result<(), FrontMatterError> {
+        let content = "---\nid: \"01\"\ntags:\n  - a\n  - b\nweight: 3\n---\nbody";
+        let actual = parse(content)?;
+
+        assert_eq!(actual.format, Some(Format::Yaml));
+        assert_eq!(actual.metadata["id"], Value::String("01".into()));
+        assert_eq!(
+            actual.metadata["tags"],
+            Value::Array(vec![Value::String("a".into()), Value::String("b".into())])
+        );
+        assert_eq!(actual.metadata["weight"], Value::Integer(3));
+        assert_eq!(actual.body, "body");
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_yaml_reports_line() {
+        let content = "---\nid: \"01\"\ntags: [a\n---\nbody";
+        let actual = parse(content).map(|_| ()).unwrap_err();
+
+        assert_eq!(actual.position().0, 4);
+    }
+
+    #[test]
+    fn parse_json_object() -> Result<(), FrontMatterError> {
+        let content = "{\n  \"id\": \"01\",\n  \"draft\": null\n}\nbody";
+        let actual = parse(content)?;
+
+        assert_eq!(actual.format, Some(Format::Json));
+        assert_eq!(actual.metadata["id"], Value::String("01".into()));
+        assert_eq!(actual.metadata["draft"], Value::Null);
+        assert_eq!(actual.body, "body");
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_json_single_line() -> Result<(), FrontMatterError> {
+        let actual = parse("{\"id\":\"01\"}\nbody")?;
+
+        assert_eq!(actual.metadata["id"], Value::String("01".into()));
+        assert_eq!(actual.body, "body");
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_leading_brace_without_front_matter() -> Result<(), FrontMatterError> {
+        for content in &[
+            "{% include x %}\nbody",
+            "{\"id\": \"01\"} trailing\nbody",
+            "{\nbody",
+            "{not json}\n}\nbody",
+        ] {
+            let actual = split(content)?;
+
+            assert_eq!(actual.front_matter, None, "{}", content);
+            assert_eq!(actual.body, *content);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_json_delimited() -> Result<(), FrontMatterError> {
+        let content = ";;;\n{\"id\": \"01\"}\n;;;\nbody";
+        let actual = parse(content)?;
+
+        assert_eq!(actual.format, Some(Format::Json));
+        assert_eq!(actual.metadata["id"], Value::String("01".into()));
+        assert_eq!(actual.body, "body");
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_json_reports_line() {
+        let content = "{\n  \"id\": \"01\",\n  \"draft\": nope\n}\nbody";
+        let actual = parse(content).map(|_| ()).unwrap_err();
+
+        assert_eq!(actual.position().0, 3);
+    }
+
+    #[test]
+    fn find_key_position() {
+        let toml = "+++\ntitle = \"Lorem\"\n  id = \"01\"\n+++\n";
+        let yaml = "---\nidentifier: 2\nid: \"01\"\n---\n";
+        let json = "{\n  \"title\": \"Lorem\",\n  \"id\": \"01\"\n}\n";
+
+        assert_eq!(key_position(toml, "id"), Some((3, 3)));
+        assert_eq!(key_position(yaml, "id"), Some((3, 1)));
+        assert_eq!(key_position(json, "id"), Some((3, 3)));
+        assert_eq!(key_position(toml, "tags"), None);
+    }
+
+    #[test]
+    fn json_roundtrip() -> Result<(), Box<dyn Error>> {
+        let content = "+++\nid = \"01\"\nweight = 2\ndate = 2020-01-02\n[extra]\nscore = 1.5\nat = [1979-05-27T07:32:00Z]\n+++\n";
+        let expected = parse(content)?.metadata;
+        let actual = from_json(&to_json(&expected))?;
+
+        assert_eq!(actual["date"], Value::Datetime("2020-01-02".into()));
+        assert_eq!(actual, expected);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,394 @@
+//! This module deals with the collection of information from Git repositories.
+//!
+//! Files are read from the object database at a given commit, never from the working tree, so
+//! uncommitted changes are left out.
+
+use crate::context::Result;
+use crate::filesystem::{self, IgnoreFiles, WalkOptions, IGNORE_FILENAME};
+use crate::source_entry::History;
+use chrono::prelude::*;
+use git2::{
+    Delta, DiffFindOptions, DiffOptions, ObjectType, Oid, Repository, Sort, TreeWalkMode,
+    TreeWalkResult,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The revision read when none is given.
+pub const DEFAULT_REVISION: &str = "HEAD";
+
+/// A commit of a Git repository along with the files found in it.
+///
+/// A repository handle can't be shared between threads so the snapshot only keeps the path of the
+/// repository and every thread reading files opens its own handle.
+pub struct Snapshot {
+    path: PathBuf,
+    commit_id: Oid,
+    files: Vec<File>,
+}
+
+/// A file found in a commit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+    /// The path relative to the repository root using `/` as separator.
+    pub path: String,
+    pub blob_id: Oid,
+    pub size: u64,
+}
+
+impl Snapshot {
+    /// Opens the repository at the given route and collects the files at the given revision, e.g.
+    /// a branch, a tag or a commit hash, matching the given options.
+    ///
+    /// `.gitignore` files play no part given that committed files are never ignored by Git, but
+    /// `.oneloignore` files found in the commit are honoured unless `ignore` is off.
+    pub fn open<P: AsRef<Path>>(route: P, revision: &str, options: &WalkOptions) -> Result<Self> {
+        let route = route.as_ref();
+        let repo = Repository::open(route)
+            .map_err(|err| format!("{}: {}", route.display(), err.message()))?;
+        let commit = repo
+            .revparse_single(revision)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|err| format!("{}@{}: {}", route.display(), revision, err.message()))?;
+        let include = filesystem::glob_set(&options.include)?;
+        let exclude = filesystem::glob_set(&options.exclude)?;
+        let mut candidates = Vec::new();
+        let mut ignore_files = Vec::new();
+
+        commit.tree()?.walk(TreeWalkMode::PreOrder, |dir, entry| {
+            let name = entry.name().unwrap_or_default();
+            let path = format!("{}{}", dir, name);
+
+            if exclude.is_match(&path) {
+                return TreeWalkResult::Skip;
+            }
+
+            match entry.kind() {
+                Some(ObjectType::Tree) if !options.recursive => TreeWalkResult::Skip,
+                Some(ObjectType::Blob) if name == IGNORE_FILENAME => {
+                    ignore_files.push((dir.to_string(), entry.id()));
+                    TreeWalkResult::Ok
+                }
+                Some(ObjectType::Blob) if include.is_match(&path) => {
+                    candidates.push((path, entry.id()));
+                    TreeWalkResult::Ok
+                }
+                _ => TreeWalkResult::Ok,
+            }
+        })?;
+
+        let mut ignores = IgnoreFiles::default();
+
+        if options.ignore {
+            for (dir, blob_id) in &ignore_files {
+                ignores.add(dir, repo.find_blob(*blob_id)?.content())?;
+            }
+        }
+
+        let odb = repo.odb()?;
+        let mut files = Vec::new();
+
+        for (path, blob_id) in candidates {
+            if !ignores.is_ignored(&path) {
+                let (size, _) = odb.read_header(blob_id)?;
+
+                files.push(File {
+                    path,
+                    blob_id,
+                    size: size as u64,
+                });
+            }
+        }
+
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let commit_id = commit.id();
+        drop(commit);
+        drop(odb);
+
+        Ok(Snapshot {
+            path: repo.path().to_path_buf(),
+            commit_id,
+            files,
+        })
+    }
+
+    /// The hash of the commit the revision resolved to.
+    pub fn commit_id(&self) -> Oid {
+        self.commit_id
+    }
+
+    /// The files found in the commit, sorted by path.
+    pub fn files(&self) -> &[File] {
+        &self.files
+    }
+
+    /// Opens a handle on the repository.
+    pub fn repository(&self) -> Result<Repository> {
+        Ok(Repository::open(&self.path)?)
+    }
+
+    /// Reads the content of a file with the given handle on the repository.
+    pub fn read(&self, repo: &Repository, file: &File) -> Result<Vec<u8>> {
+        Ok(repo.find_blob(file.blob_id)?.content().to_vec())
+    }
+
+    /// Walks the history leading to the commit to find when each file was added and last
+    /// changed, and by whom, keyed by path.
+    ///
+    /// Merge commits are compared against their first parent only. A file renamed along the way
+    /// keeps the history of its former name.
+    pub fn history(&self) -> Result<HashMap<String, History>> {
+        let repo = self.repository()?;
+        let mut revwalk = repo.revwalk()?;
+        let mut history: HashMap<String, History> = HashMap::new();
+        // The files still missing their creation, keyed by their name at the commit walked.
+        let mut pending: HashMap<String, &str> = self
+            .files
+            .iter()
+            .map(|f| (f.path.clone(), f.path.as_str()))
+            .collect();
+
+        revwalk.push(self.commit_id)?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+
+        for oid in revwalk {
+            if pending.is_empty() {
+                break;
+            }
+
+            let commit = repo.find_commit(oid?)?;
+            let parent = match commit.parent_count() {
+                0 => None,
+                _ => Some(commit.parent(0)?.tree()?),
+            };
+            let tree = commit.tree()?;
+            let mut options = DiffOptions::new();
+
+            // Only the files still missing their creation are compared, taken literally.
+            options.disable_pathspec_match(true);
+
+            for path in pending.keys() {
+                options.pathspec(path);
+            }
+
+            let diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&tree), Some(&mut options))?;
+            let time = Utc.timestamp(commit.time().seconds(), 0);
+            let author = commit.author().name().unwrap_or_default().to_string();
+            let mut added = Vec::new();
+
+            for delta in diff.deltas() {
+                let path = match delta.new_file().path().and_then(|path| path.to_str()) {
+                    Some(path) => path,
+                    None => continue,
+                };
+                let original = match pending.get(path) {
+                    Some(original) => *original,
+                    None => continue,
+                };
+                // Commits are walked from the most recent so the first one found is the last
+                // change and the one adding the file ends its history.
+                let entry = history
+                    .entry(original.to_string())
+                    .or_insert_with(|| History {
+                        created: time,
+                        updated: time,
+                        authors: Vec::new(),
+                    });
+
+                entry.created = time;
+
+                if !entry.authors.contains(&author) {
+                    entry.authors.push(author.clone());
+                }
+
+                if delta.status() == Delta::Added {
+                    added.push(path.to_string());
+                }
+            }
+
+            if added.is_empty() {
+                continue;
+            }
+
+            // The old name of a renamed file is outside the pathspec, so renames are looked for
+            // in the whole commit, only when a pending file appears in it.
+            let renames = if parent.is_some() {
+                let mut diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&tree), None)?;
+                diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+                diff.deltas()
+                    .filter(|delta| delta.status() == Delta::Renamed)
+                    .filter_map(|delta| {
+                        let old = delta.old_file().path()?.to_str()?;
+                        let new = delta.new_file().path()?.to_str()?;
+
+                        Some((new.to_string(), old.to_string()))
+                    })
+                    .collect()
+            } else {
+                HashMap::new()
+            };
+
+            for path in added {
+                if let Some(original) = pending.remove(&path) {
+                    if let Some(old) = renames.get(&path) {
+                        pending.insert(old.clone(), original);
+                    }
+                }
+            }
+        }
+
+        Ok(history)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+    use git2::Signature;
+    use std::fs;
+
+    /// Commits the given files on top of the current head.
+    pub(crate) fn commit(repo: &Repository, files: &[(&str, &str)], message: &str) -> Result<Oid> {
+        let signature = Signature::now("Onelo", "onelo@example.org")?;
+
+        commit_as(repo, &signature, files, message)
+    }
+
+    /// Commits the given files on top of the current head with the given signature.
+    pub(crate) fn commit_as(
+        repo: &Repository,
+        signature: &Signature,
+        files: &[(&str, &str)],
+        message: &str,
+    ) -> Result<Oid> {
+        let root = repo.workdir().ok_or("Bare repository")?;
+        let mut index = repo.index()?;
+
+        for (path, content) in files {
+            let file = root.join(path);
+            fs::create_dir_all(file.parent().ok_or("No parent")?)?;
+            fs::write(file, content)?;
+            index.add_path(Path::new(path))?;
+        }
+
+        index.write()?;
+
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let parents = match repo.head() {
+            Ok(head) => vec![head.peel_to_commit()?],
+            Err(_) => Vec::new(),
+        };
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+
+        Ok(repo.commit(Some("HEAD"), signature, signature, message, &tree, &parents)?)
+    }
+
+    #[test]
+    fn read_files_at_revision() -> Result<()> {
+        let dir = TempDir::new("git")?;
+        let repo = Repository::init(&dir)?;
+
+        let first = commit(
+            &repo,
+            &[
+                ("a.md", "# A"),
+                ("notes.txt", "Not Markdown"),
+                ("sub/b.md", "# B"),
+                ("sub/.oneloignore", "draft.md\n"),
+                ("sub/draft.md", "# Draft"),
+            ],
+            "First",
+        )?;
+        repo.tag_lightweight("v1", &repo.find_object(first, None)?, false)?;
+        commit(&repo, &[("a.md", "# A2"), ("c.md", "# C")], "Second")?;
+        fs::write(dir.join("d.md"), "# Uncommitted")?;
+
+        let tagged = Snapshot::open(&dir, "v1", &WalkOptions::default())?;
+        let head = Snapshot::open(&dir, DEFAULT_REVISION, &WalkOptions::default())?;
+        let flat = Snapshot::open(
+            &dir,
+            "v1",
+            &WalkOptions {
+                recursive: false,
+                ..WalkOptions::default()
+            },
+        )?;
+        let paths = |snapshot: &Snapshot| -> Vec<String> {
+            snapshot.files().iter().map(|f| f.path.clone()).collect()
+        };
+        let content = tagged.read(&tagged.repository()?, &tagged.files()[0])?;
+        let missing = Snapshot::open(&dir, "v2", &WalkOptions::default());
+
+        assert_eq!(tagged.commit_id(), first);
+        assert_eq!(paths(&tagged), vec!["a.md", "sub/b.md"]);
+        assert_eq!(paths(&head), vec!["a.md", "c.md", "sub/b.md"]);
+        assert_eq!(paths(&flat), vec!["a.md"]);
+        assert_eq!(content, b"# A");
+        assert!(missing.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn walk_history() -> Result<()> {
+        let dir = TempDir::new("git-history")?;
+        let repo = Repository::init(&dir)?;
+        let ada = |time| Signature::new("Ada", "ada@example.org", &git2::Time::new(time, 0));
+        let bob = |time| Signature::new("Bob", "bob@example.org", &git2::Time::new(time, 0));
+
+        commit_as(&repo, &ada(1000)?, &[("a.md", "1"), ("b.md", "1")], "First")?;
+        commit_as(&repo, &bob(2000)?, &[("a.md", "2")], "Second")?;
+        commit_as(&repo, &ada(3000)?, &[("a.md", "3"), ("c.md", "1")], "Third")?;
+
+        let snapshot = Snapshot::open(&dir, DEFAULT_REVISION, &WalkOptions::default())?;
+        let history = snapshot.history()?;
+
+        assert_eq!(
+            history["a.md"],
+            History {
+                created: Utc.timestamp(1000, 0),
+                updated: Utc.timestamp(3000, 0),
+                authors: vec!["Ada".into(), "Bob".into()],
+            }
+        );
+        assert_eq!(history["b.md"].updated, Utc.timestamp(1000, 0));
+        assert_eq!(history["c.md"].created, Utc.timestamp(3000, 0));
+        assert_eq!(history.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn walk_history_across_renames() -> Result<()> {
+        let dir = TempDir::new("git-renames")?;
+        let repo = Repository::init(&dir)?;
+        let ada = |time| Signature::new("Ada", "ada@example.org", &git2::Time::new(time, 0));
+        let bob = |time| Signature::new("Bob", "bob@example.org", &git2::Time::new(time, 0));
+        let content = "# Bravo\n\nA note long enough to be recognised once moved.\n";
+
+        commit_as(&repo, &ada(1000)?, &[("b.md", content)], "First")?;
+
+        let mut index = repo.index()?;
+        index.remove_path(Path::new("b.md"))?;
+        index.write()?;
+        fs::remove_file(dir.join("b.md"))?;
+        commit_as(&repo, &bob(2000)?, &[("notes/b.md", content)], "Move")?;
+
+        let snapshot = Snapshot::open(&dir, DEFAULT_REVISION, &WalkOptions::default())?;
+        let history = snapshot.history()?;
+
+        assert_eq!(
+            history["notes/b.md"],
+            History {
+                created: Utc.timestamp(1000, 0),
+                updated: Utc.timestamp(2000, 0),
+                authors: vec!["Bob".into(), "Ada".into()],
+            }
+        );
+        assert_eq!(history.len(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+//! This module is concerned with the graph as a whole, as consumed by the transformation phase.
+
+use crate::cache;
+use crate::connection::ConnectionType;
+use crate::context::Result;
+use crate::front_matter::Metadata;
+use crate::source_entry::History;
+use rusqlite::Connection;
+use std::ops::Range;
+
+/// A node along with the information about the entry it was extracted from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    pub id: String,
+    pub source_id: String,
+    pub path: String,
+    pub title: String,
+    pub metadata: Metadata,
+    /// The checksum of the content the node was extracted from.
+    pub content_id: String,
+    /// The history of the entry, if its source is a Git repository.
+    pub history: Option<History>,
+}
+
+impl GraphNode {
+    /// The label to display, the front matter `label` if any or the title otherwise.
+    pub fn label(&self) -> &str {
+        self.metadata
+            .get("label")
+            .and_then(|label| label.as_str())
+            .unwrap_or(&self.title)
+    }
+}
+
+/// A connection resolved to a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub origin_id: String,
+    pub target_id: String,
+    pub connection_type: ConnectionType,
+    /// The text of the connection, e.g. a link text.
+    pub title: String,
+    /// The byte span of the connection in the origin content.
+    pub span: Range<usize>,
+}
+
+/// The directed graph of nodes and resolved connections.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// The identifiers of the sources with at least one node, in order.
+    pub fn source_ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.nodes.iter().map(|n| n.source_id.as_str()).collect();
+        ids.dedup();
+
+        ids
+    }
+}
+
+/// Loads the graph from the cache. Nodes are sorted by source and path.
+pub fn load(conn: &Connection) -> Result<Graph> {
+    Ok(Graph {
+        nodes: cache::get_graph_nodes(conn)?,
+        edges: cache::get_graph_edges(conn)?,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::front_matter::Value;
+
+    /// Builds a node with the given identifier, also used as its path, and a placeholder checksum.
+    pub(crate) fn node(id: &str, source_id: &str, title: &str) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            source_id: source_id.to_string(),
+            path: id.to_string(),
+            title: title.to_string(),
+            metadata: Metadata::new(),
+            content_id: "1e20".to_string(),
+            history: None,
+        }
+    }
+
+    impl GraphNode {
+        /// Sets a front matter attribute.
+        pub(crate) fn with(mut self, key: &str, value: Value) -> Self {
+            self.metadata.insert(key.to_string(), value);
+            self
+        }
+    }
+}
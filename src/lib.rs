@@ -1,9 +1,27 @@
+pub mod archive;
 pub mod artefact;
+pub mod backlinks;
 pub mod cache;
+pub mod check;
 pub mod checksum;
 pub mod cli;
+pub mod config;
+pub mod connection;
 pub mod content_type;
 pub mod context;
+pub mod diff;
+pub mod extraction;
 pub mod filesystem;
+pub mod front_matter;
+pub mod git;
+pub mod graph;
+pub mod markdown;
+pub mod node;
+pub mod pipeline;
+pub mod resolution;
+pub mod serve;
 pub mod source;
 pub mod source_entry;
+pub mod sourcing;
+pub mod transformation;
+pub mod watch;
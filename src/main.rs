@@ -1,9 +1,14 @@
 use clap::{AppSettings, Clap};
-use onelo_backend::cli::build;
+use onelo_backend::cli::{backlinks, build, check, diff, serve};
+use std::process;
 
 #[derive(Debug, Clap)]
 enum Subcommand {
+    Backlinks(backlinks::Cmd),
     Build(build::Cmd),
+    Check(check::Cmd),
+    Diff(diff::Cmd),
+    Serve(serve::Cmd),
 }
 
 #[derive(Debug, Clap)]
@@ -16,14 +21,21 @@ struct Cli {
 fn main() {
     let cli: Cli = Cli::parse();
 
-    match cli.subcommand {
-        Subcommand::Build(cmd) => match cmd.run() {
-            Ok(msg) => {
-                println!("{}", msg);
-            }
-            Err(err) => {
-                eprintln!("{:?}", err);
-            }
-        },
+    let result = match cli.subcommand {
+        Subcommand::Backlinks(cmd) => cmd.run(),
+        Subcommand::Build(cmd) => cmd.run(),
+        Subcommand::Check(cmd) => cmd.run(),
+        Subcommand::Diff(cmd) => cmd.run(),
+        Subcommand::Serve(cmd) => cmd.run(),
+    };
+
+    match result {
+        Ok(msg) => {
+            println!("{}", msg);
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
     }
 }
@@ -0,0 +1,372 @@
+//! This module is concerned with parsing Markdown content.
+
+use crate::connection::ConnectionType;
+use lazy_static::lazy_static;
+use pulldown_cmark::{html, Event, LinkType, Options, Parser, Tag};
+use regex::Regex;
+use std::collections::HashSet;
+use std::ops::Range;
+
+lazy_static! {
+    static ref WIKILINK: Regex = Regex::new(r"\[\[([^\[\]\n]+?)\]\]")
+        .expect("Something went wrong when compiling a regular expression.");
+}
+
+/// A link found in a Markdown document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub connection_type: ConnectionType,
+    /// The destination as written, e.g. `bar.md` in `[foo](bar.md)`.
+    pub href: String,
+    /// The plain text of the link, e.g. `foo` in `[foo](bar.md)`.
+    pub text: String,
+    /// The byte span of the whole link in the document.
+    pub span: Range<usize>,
+}
+
+/// Extracts every inline, reference and autolink link from the given Markdown document.
+///
+/// Images are not considered links.
+pub fn links(document: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut current: Option<Link> = None;
+
+    for (event, range) in Parser::new_ext(document, Options::empty()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link(link_type, href, _)) => {
+                current = Some(Link {
+                    connection_type: connection_type(link_type),
+                    href: href.to_string(),
+                    text: String::new(),
+                    span: range,
+                });
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(link) = current.as_mut() {
+                    link.text.push_str(&text);
+                }
+            }
+            Event::End(Tag::Link(..)) => {
+                if let Some(link) = current.take() {
+                    links.push(link);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    links
+}
+
+/// Extracts every wikilink from the given Markdown document.
+///
+/// The `href` of a wikilink is its target including any heading anchor or block reference, e.g.
+/// `foo#bar` in `[[foo#bar|baz]]`. The text is the alias if any, the target otherwise.
+///
+/// Wikilinks in code spans and code blocks are ignored.
+pub fn wikilinks(document: &str) -> Vec<Link> {
+    let code = code_ranges(document);
+
+    WIKILINK
+        .captures_iter(document)
+        .filter_map(|captures| {
+            let whole = captures.get(0)?;
+            let inner = captures.get(1)?.as_str();
+
+            if code
+                .iter()
+                .any(|range| range.start <= whole.start() && whole.end() <= range.end)
+            {
+                return None;
+            }
+
+            let (href, text) = match inner.find('|') {
+                Some(i) => (inner[..i].trim(), inner[i + 1..].trim()),
+                None => (inner.trim(), inner.trim()),
+            };
+
+            Some(Link {
+                connection_type: ConnectionType::Wikilink,
+                href: href.to_string(),
+                text: text.to_string(),
+                span: whole.range(),
+            })
+        })
+        .collect()
+}
+
+/// Finds the byte ranges of code spans and code blocks.
+fn code_ranges(document: &str) -> Vec<Range<usize>> {
+    Parser::new_ext(document, Options::empty())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Code(_) | Event::Start(Tag::CodeBlock(_)) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Renders the given Markdown document as HTML.
+///
+/// Every link and wikilink is given to `resolve`, which returns the URL to point it to. Links it
+/// doesn't resolve keep their destination and wikilinks it doesn't resolve become plain text. Links
+/// are given with their span in the document but without text.
+pub fn to_html<F>(document: &str, mut resolve: F) -> String
+where
+    F: FnMut(&Link) -> Option<String>,
+{
+    let mut source = String::with_capacity(document.len());
+    // The end of each wikilink replacement in `source` along with the accumulated shift.
+    let mut shifts: Vec<(usize, isize)> = Vec::new();
+    let mut replaced = HashSet::new();
+    let mut last = 0;
+
+    for link in wikilinks(document) {
+        source.push_str(&document[last..link.span.start]);
+
+        match resolve(&link) {
+            Some(url) => {
+                replaced.insert(source.len());
+                source.push_str(&format!(
+                    "[{}]({})",
+                    escape(&link.text),
+                    encode_destination(&url)
+                ));
+            }
+            None => source.push_str(&escape(&link.text)),
+        }
+
+        last = link.span.end;
+        shifts.push((source.len(), source.len() as isize - last as isize));
+    }
+
+    source.push_str(&document[last..]);
+
+    let original = |position: usize| {
+        let shift = shifts
+            .iter()
+            .take_while(|(end, _)| *end <= position)
+            .last()
+            .map_or(0, |(_, shift)| *shift);
+
+        (position as isize - shift) as usize
+    };
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let events = Parser::new_ext(&source, options)
+        .into_offset_iter()
+        .map(|(event, range)| match event {
+            Event::Start(Tag::Link(link_type, href, title)) if !replaced.contains(&range.start) => {
+                let link = Link {
+                    connection_type: connection_type(link_type),
+                    href: href.to_string(),
+                    text: String::new(),
+                    span: original(range.start)..original(range.end),
+                };
+                let href = resolve(&link).map(Into::into).unwrap_or(href);
+
+                Event::Start(Tag::Link(link_type, href, title))
+            }
+            event => event,
+        });
+    let mut out = String::new();
+
+    html::push_html(&mut out, events);
+
+    out
+}
+
+/// Escapes the characters with a meaning in Markdown inline content.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if "\\`*_{}[]<>()#+-.!|~&".contains(c) {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Encodes the characters that would end a Markdown link destination.
+fn encode_destination(url: &str) -> String {
+    url.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+        .replace('<', "%3C")
+        .replace('>', "%3E")
+}
+
+/// Extracts the text of the first heading of the given Markdown document, if any.
+pub fn title(document: &str) -> Option<String> {
+    let mut title: Option<String> = None;
+
+    for event in Parser::new_ext(document, Options::empty()) {
+        match event {
+            Event::Start(Tag::Heading(_)) => title = Some(String::new()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(title) = title.as_mut() {
+                    title.push_str(&text);
+                }
+            }
+            Event::End(Tag::Heading(_)) => break,
+            _ => {}
+        }
+    }
+
+    title
+}
+
+fn connection_type(link_type: LinkType) -> ConnectionType {
+    match link_type {
+        LinkType::Inline => ConnectionType::Inline,
+        LinkType::Autolink | LinkType::Email => ConnectionType::Autolink,
+        _ => ConnectionType::Reference,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_link() {
+        let document = "Lorem [ipsum `dolor`](sit.md \"amet\") consectetur.";
+        let actual = links(document);
+
+        assert_eq!(
+            actual,
+            vec![Link {
+                connection_type: ConnectionType::Inline,
+                href: "sit.md".into(),
+                text: "ipsum dolor".into(),
+                span: 6..36,
+            }]
+        );
+        assert_eq!(
+            &document[actual[0].span.clone()],
+            "[ipsum `dolor`](sit.md \"amet\")"
+        );
+    }
+
+    #[test]
+    fn reference_links() {
+        let document = "[full][a], [collapsed][] and [shortcut].\n\n[a]: a.md\n[collapsed]: b.md\n[shortcut]: c.md\n";
+        let actual: Vec<(ConnectionType, String)> = links(document)
+            .into_iter()
+            .map(|link| (link.connection_type, link.href))
+            .collect();
+
+        assert_eq!(
+            actual,
+            vec![
+                (ConnectionType::Reference, "a.md".into()),
+                (ConnectionType::Reference, "b.md".into()),
+                (ConnectionType::Reference, "c.md".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn autolinks() {
+        let document = "See <https://example.org> or <foo@example.org>.";
+        let actual: Vec<(ConnectionType, String)> = links(document)
+            .into_iter()
+            .map(|link| (link.connection_type, link.href))
+            .collect();
+
+        assert_eq!(
+            actual,
+            vec![
+                (ConnectionType::Autolink, "https://example.org".into()),
+                (ConnectionType::Autolink, "foo@example.org".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn images_are_not_links() {
+        let actual = links("![alt](image.png) and `[code](no.md)`");
+
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    fn wikilink_variants() {
+        let document = "[[Note Title]], [[note|alias]], [[note#Section]] and [[note^id]].";
+        let actual: Vec<(String, String)> = wikilinks(document)
+            .into_iter()
+            .map(|link| (link.href, link.text))
+            .collect();
+
+        assert_eq!(
+            actual,
+            vec![
+                ("Note Title".into(), "Note Title".into()),
+                ("note".into(), "alias".into()),
+                ("note#Section".into(), "note#Section".into()),
+                ("note^id".into(), "note^id".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn wikilink_span() {
+        let document = "Lorem [[ipsum]] dolor.";
+        let actual = wikilinks(document);
+
+        assert_eq!(actual[0].connection_type, ConnectionType::Wikilink);
+        assert_eq!(&document[actual[0].span.clone()], "[[ipsum]]");
+    }
+
+    #[test]
+    fn wikilinks_in_code_are_ignored() {
+        let document = "`[[span]]`\n\n```\n[[block]]\n```\n\n[[real]]";
+        let actual: Vec<String> = wikilinks(document)
+            .into_iter()
+            .map(|link| link.href)
+            .collect();
+
+        assert_eq!(actual, vec!["real".to_string()]);
+    }
+
+    #[test]
+    fn first_heading_title() {
+        let actual = title("Intro\n\n# Lorem *ipsum*\n\n## Dolor");
+
+        assert_eq!(actual, Some("Lorem ipsum".into()));
+    }
+
+    #[test]
+    fn html_with_resolved_links() {
+        let document = "See [a](a.md), [[b|the b]], [[c]] and `[[d]]`.\n";
+        let actual = to_html(document, |link| match link.href.as_str() {
+            "a.md" => Some(format!("a.html@{}", link.span.start)),
+            "b" => Some("b c.html".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(
+            actual,
+            "<p>See <a href=\"a.html@4\">a</a>, <a href=\"b%20c.html\">the b</a>, c and <code>[[d]]</code>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn html_span_after_wikilink() {
+        let document = "[[a]] then [b](b.md)";
+        let mut spans = Vec::new();
+
+        to_html(document, |link| {
+            spans.push(link.span.clone());
+            Some("x.html".to_string())
+        });
+
+        assert_eq!(spans, vec![0..5, 11..20]);
+    }
+}
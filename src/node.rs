@@ -0,0 +1,37 @@
+//! This module is concerned with the nodes of the graph.
+
+use crate::checksum::Checksum;
+
+/// A node of the graph.
+///
+/// Every source entry with a content type Onelo can parse becomes a node. Nodes share their
+/// identifier with the source entry they were extracted from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    id: String,
+    title: String,
+    /// The content the node was extracted from.
+    content_id: Checksum,
+}
+
+impl Node {
+    pub fn new(id: String, title: String, content_id: Checksum) -> Self {
+        Node {
+            id,
+            title,
+            content_id,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn content_id(&self) -> &Checksum {
+        &self.content_id
+    }
+}
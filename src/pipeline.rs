@@ -0,0 +1,148 @@
+//! This module is concerned with running the transformation pipeline phases in order.
+
+use crate::cache;
+use crate::context::{Context, Result};
+use crate::extraction::{self, Extractors};
+use crate::resolution;
+use crate::source::Source;
+use crate::sourcing;
+use rusqlite::Connection;
+use std::fmt;
+
+/// A summary of every phase.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Report {
+    pub sourcing: sourcing::Report,
+    pub extraction: extraction::Report,
+    pub resolution: resolution::Report,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\n{}\n{}",
+            self.sourcing, self.extraction, self.resolution
+        )?;
+
+        for invalid in &self.sourcing.invalid {
+            write!(f, "\nSkipped {}", invalid)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs the prelude, sourcing, extraction and resolution phases against the given cache in a
+/// single transaction.
+///
+/// Sources found in the cache but not given are removed along with their entries. Entries with a
+/// malformed front matter are left out and listed in the sourcing report, so the rest of the graph
+/// is still built.
+pub fn run(conn: &mut Connection, sources: &[Source], extractors: &Extractors) -> Result<Report> {
+    for (i, source) in sources.iter().enumerate() {
+        if sources[..i].iter().any(|other| other.id() == source.id()) {
+            return Err(format!("Duplicate source `{}`", source.id()).into());
+        }
+    }
+
+    cache::bootstrap(conn)?;
+
+    let tx = conn.transaction()?;
+    cache::insert_context(&tx, &Context::new())?;
+
+    let mut report = Report {
+        sourcing: sourcing::Report {
+            removed: sourcing::prune(&tx, sources)?,
+            ..sourcing::Report::default()
+        },
+        ..Report::default()
+    };
+
+    for source in sources {
+        let sourcing = sourcing::run(&tx, source)?;
+
+        report.sourcing.added += sourcing.added;
+        report.sourcing.changed += sourcing.changed;
+        report.sourcing.removed += sourcing.removed;
+        report.sourcing.unchanged += sourcing.unchanged;
+        report.sourcing.skipped += sourcing.skipped;
+        report.sourcing.invalid.extend(sourcing.invalid);
+    }
+
+    report.extraction = extraction::run(&tx, extractors)?;
+    report.resolution = resolution::run(&tx)?;
+
+    tx.commit()?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+    use rusqlite::NO_PARAMS;
+    use std::fs;
+
+    #[test]
+    fn run_multiple_sources() -> Result<()> {
+        let dir = TempDir::new("pipeline")?;
+        fs::create_dir_all(dir.join("one"))?;
+        fs::create_dir_all(dir.join("two"))?;
+        fs::write(dir.join("one/a.md"), "See [[two:a]].")?;
+        fs::write(dir.join("two/a.md"), "Nothing here.")?;
+
+        let one = Source::new("one".parse()?, dir.join("one"));
+        let two = Source::new("two".parse()?, dir.join("two"));
+        let mut conn = cache::connect(":memory:")?;
+
+        let first = run(
+            &mut conn,
+            &[one.clone(), two.clone()],
+            &Extractors::default(),
+        )?;
+        let second = run(
+            &mut conn,
+            std::slice::from_ref(&one),
+            &Extractors::default(),
+        )?;
+        let duplicate = run(&mut conn, &[one.clone(), one], &Extractors::default());
+        let sources: i64 =
+            conn.query_row("SELECT count(*) FROM source", NO_PARAMS, |row| row.get(0))?;
+
+        assert_eq!(first.sourcing.added, 2);
+        assert_eq!(first.resolution.resolved, 1);
+        assert_eq!(second.sourcing.removed, 1);
+        assert_eq!(second.resolution.unresolved.len(), 1);
+        assert_eq!(sources, 1);
+        assert_eq!(
+            duplicate.map_err(|err| err.to_string()).err(),
+            Some("Duplicate source `one`".into())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn run_skipping_malformed_entries() -> Result<()> {
+        let dir = TempDir::new("pipeline-malformed")?;
+        fs::write(dir.join("a.md"), "See [[b]].")?;
+        fs::write(dir.join("b.md"), "---\ntitle: B\n")?;
+
+        let source = Source::new("test".parse()?, &dir);
+        let mut conn = cache::connect(":memory:")?;
+        let report = run(&mut conn, &[source], &Extractors::default())?;
+        let nodes: i64 =
+            conn.query_row("SELECT count(*) FROM node", NO_PARAMS, |row| row.get(0))?;
+
+        assert_eq!(report.sourcing.added, 1);
+        assert_eq!(report.sourcing.invalid.len(), 1);
+        assert_eq!(nodes, 1);
+        assert!(report
+            .to_string()
+            .ends_with("b.md:1:1: The front matter is missing its closing `---`"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,534 @@
+//! This module is concerned with resolving the targets of connections to nodes.
+//!
+//! A target of the form `source_id:path` where `source_id` is a known source identifier resolves
+//! to the entry with that path in that source. Any other target without a scheme resolves
+//! relative to the origin entry within its own source.
+//!
+//! Wikilink targets resolve by file stem, front matter `id` or title, case insensitively. When
+//! several nodes match, those in the origin source are preferred.
+
+use crate::cache;
+use crate::connection::ConnectionType;
+use crate::context::Result;
+use crate::source_entry::{stem, SourceEntry};
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+
+/// The outcome of resolving a connection target.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// The target is the node with the given identifier.
+    Resolved(String),
+    /// The target looks like a node but there is no such node.
+    Unresolved,
+    /// The target matches more than one node.
+    Ambiguous(Vec<String>),
+    /// The target is outside the graph, e.g. an `https` URL.
+    External,
+}
+
+/// A connection whose target could not be resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unresolved {
+    pub origin_id: String,
+    pub target: String,
+    pub span: Range<usize>,
+    /// The nodes the target could refer to when it is ambiguous.
+    pub candidates: Vec<String>,
+}
+
+impl Unresolved {
+    pub fn is_ambiguous(&self) -> bool {
+        !self.candidates.is_empty()
+    }
+}
+
+/// A summary of the resolution phase.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Report {
+    pub resolved: usize,
+    pub external: usize,
+    pub unresolved: Vec<Unresolved>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ambiguous = self.unresolved.iter().filter(|u| u.is_ambiguous()).count();
+
+        write!(
+            f,
+            "{} connections resolved, {} unresolved, {} ambiguous, {} external.",
+            self.resolved,
+            self.unresolved.len() - ambiguous,
+            ambiguous,
+            self.external
+        )
+    }
+}
+
+/// The set of known sources and entries to resolve targets against.
+#[derive(Debug, Default)]
+pub struct Index {
+    sources: HashSet<String>,
+    /// Node identifiers keyed by source identifier and path.
+    entries: HashMap<(String, String), String>,
+    /// Source and node identifiers keyed by lowercase stem, front matter id and title.
+    names: HashMap<String, Vec<(String, String)>>,
+}
+
+impl Index {
+    /// Loads the known sources and nodes from the cache.
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let mut index = Index::default();
+
+        for source_id in cache::get_source_ids(conn)? {
+            index.sources.insert(source_id);
+        }
+
+        for keys in cache::get_node_keys(conn)? {
+            let mut names = vec![stem(&keys.path).to_string(), keys.title.clone()];
+            names.extend(keys.front_matter_id.clone());
+
+            index.insert(&keys.source_id, &keys.path, &keys.id, &names);
+        }
+
+        Ok(index)
+    }
+
+    fn insert(&mut self, source_id: &str, path: &str, id: &str, names: &[String]) {
+        self.entries
+            .insert((source_id.to_string(), path.to_string()), id.to_string());
+
+        for name in names {
+            let candidates = self.names.entry(name.to_lowercase()).or_default();
+            let candidate = (source_id.to_string(), id.to_string());
+
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+
+    /// Resolves a target as found in the given origin.
+    pub fn resolve(
+        &self,
+        origin_source: &str,
+        origin_path: &str,
+        target: &str,
+        connection_type: ConnectionType,
+    ) -> Resolution {
+        if connection_type == ConnectionType::Wikilink {
+            return self.resolve_wikilink(origin_source, origin_path, target);
+        }
+
+        let target = strip_fragment(target);
+
+        if target.is_empty() {
+            return self.lookup(origin_source, origin_path);
+        }
+
+        if let Some(i) = target.find(':') {
+            let prefix = &target[..i];
+
+            if self.sources.contains(prefix) {
+                return match target.parse::<SourceEntry>() {
+                    Ok(entry) => {
+                        let path = entry.path().trim_start_matches('/');
+
+                        match normalise(&percent_decode(path)) {
+                            Some(path) => self.lookup(entry.source_id().as_str(), &path),
+                            None => Resolution::Unresolved,
+                        }
+                    }
+                    Err(_) => Resolution::Unresolved,
+                };
+            }
+
+            if is_scheme(prefix) {
+                return Resolution::External;
+            }
+        }
+
+        // Autolinks without a known source are either URLs or email addresses.
+        if connection_type == ConnectionType::Autolink {
+            return Resolution::External;
+        }
+
+        let path = if let Some(absolute) = target.strip_prefix('/') {
+            absolute.to_string()
+        } else {
+            match origin_path.rfind('/') {
+                Some(i) => format!("{}/{}", &origin_path[..i], target),
+                None => target.to_string(),
+            }
+        };
+
+        match normalise(&percent_decode(&path)) {
+            Some(path) => self.lookup(origin_source, &path),
+            None => Resolution::Unresolved,
+        }
+    }
+
+    fn resolve_wikilink(&self, origin_source: &str, origin_path: &str, target: &str) -> Resolution {
+        let page = match target.find(['#', '^']) {
+            Some(i) => target[..i].trim(),
+            None => target.trim(),
+        };
+
+        if page.is_empty() {
+            return self.lookup(origin_source, origin_path);
+        }
+
+        let (scope, page) = match page.find(':') {
+            Some(i) if self.sources.contains(&page[..i]) => (Some(&page[..i]), &page[i + 1..]),
+            _ => (None, page),
+        };
+
+        if page.contains('/') {
+            let source_id = scope.unwrap_or(origin_source);
+
+            return match normalise(page) {
+                Some(path) => match self.lookup(source_id, &path) {
+                    Resolution::Unresolved => self.lookup(source_id, &format!("{}.md", path)),
+                    resolution => resolution,
+                },
+                None => Resolution::Unresolved,
+            };
+        }
+
+        let candidates: Vec<&(String, String)> = self
+            .names
+            .get(&page.to_lowercase())
+            .map(|candidates| {
+                candidates
+                    .iter()
+                    .filter(|(source_id, _)| scope.is_none() || scope == Some(source_id.as_str()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let local: Vec<&&(String, String)> = candidates
+            .iter()
+            .filter(|(source_id, _)| source_id == origin_source)
+            .collect();
+
+        match (candidates.as_slice(), local.as_slice()) {
+            ([], _) => Resolution::Unresolved,
+            ([(_, id)], _) | (_, [(_, id)]) => Resolution::Resolved(id.clone()),
+            _ => {
+                let mut ids: Vec<String> = candidates.iter().map(|(_, id)| id.clone()).collect();
+                ids.sort();
+
+                Resolution::Ambiguous(ids)
+            }
+        }
+    }
+
+    fn lookup(&self, source_id: &str, path: &str) -> Resolution {
+        match self.entries.get(&(source_id.to_string(), path.to_string())) {
+            Some(id) => Resolution::Resolved(id.clone()),
+            None => Resolution::Unresolved,
+        }
+    }
+}
+
+/// Resolves the target of every connection in the cache.
+///
+/// Every connection is resolved again, regardless of whether its origin changed, given that
+/// adding or removing a node can change the outcome for any connection.
+pub fn run(conn: &Connection) -> Result<Report> {
+    let index = Index::load(conn)?;
+    let mut report = Report::default();
+
+    for connection in cache::get_connection_targets(conn)? {
+        let resolution = index.resolve(
+            &connection.source_id,
+            &connection.origin_path,
+            &connection.target,
+            connection.connection_type,
+        );
+        let target_id = match resolution {
+            Resolution::Resolved(id) => {
+                report.resolved += 1;
+                Some(id)
+            }
+            Resolution::External => {
+                report.external += 1;
+                None
+            }
+            Resolution::Unresolved => {
+                report.unresolved.push(Unresolved {
+                    origin_id: connection.origin_id.clone(),
+                    target: connection.target.clone(),
+                    span: connection.span.clone(),
+                    candidates: Vec::new(),
+                });
+                None
+            }
+            Resolution::Ambiguous(candidates) => {
+                report.unresolved.push(Unresolved {
+                    origin_id: connection.origin_id.clone(),
+                    target: connection.target.clone(),
+                    span: connection.span.clone(),
+                    candidates,
+                });
+                None
+            }
+        };
+
+        cache::update_connection_target(
+            conn,
+            &connection.origin_id,
+            connection.span.start,
+            &connection.connection_type,
+            target_id.as_deref(),
+        )?;
+    }
+
+    Ok(report)
+}
+
+/// Drops the fragment and query of a target, e.g. `foo.md#bar` becomes `foo.md`.
+fn strip_fragment(target: &str) -> &str {
+    match target.find(['#', '?']) {
+        Some(i) => &target[..i],
+        None => target,
+    }
+}
+
+/// Whether the given prefix is a URI scheme as defined by RFC 3986.
+fn is_scheme(prefix: &str) -> bool {
+    let mut chars = prefix.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+/// Collapses `.` and `..` segments. Returns `None` if the path escapes the source root.
+fn normalise(path: &str) -> Option<String> {
+    let mut segments: Vec<&str> = Vec::new();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    Some(segments.join("/"))
+}
+
+/// Decodes `%XX` sequences. Invalid sequences are kept as they are.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                decoded.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|d| d as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+
+    fn index() -> Index {
+        let mut index = Index::default();
+
+        index.sources.insert("notes".into());
+        index.sources.insert("wiki".into());
+
+        for (source, path, title) in &[
+            ("notes", "a.md", "Alpha"),
+            ("notes", "sub/b.md", "Beta"),
+            ("notes", "with space.md", "Spaced"),
+            ("notes", "sub/dup.md", "Duplicate"),
+            ("notes", "other/dup.md", "Duplicate"),
+            ("wiki", "a.md", "Wiki alpha"),
+            ("wiki", "c.md", "Gamma"),
+        ] {
+            let names = vec![stem(path).to_string(), title.to_string()];
+
+            index.insert(source, path, &format!("{}:{}", source, path), &names);
+        }
+
+        index
+    }
+
+    fn resolve(target: &str) -> Resolution {
+        index().resolve("notes", "sub/b.md", target, ConnectionType::Inline)
+    }
+
+    #[test]
+    fn qualified_target() {
+        assert_eq!(
+            resolve("wiki:a.md"),
+            Resolution::Resolved("wiki:a.md".into())
+        );
+        assert_eq!(
+            resolve("wiki:/a.md#top"),
+            Resolution::Resolved("wiki:a.md".into())
+        );
+        assert_eq!(resolve("wiki:b.md"), Resolution::Unresolved);
+    }
+
+    #[test]
+    fn relative_target() {
+        assert_eq!(
+            resolve("../a.md"),
+            Resolution::Resolved("notes:a.md".into())
+        );
+        assert_eq!(
+            resolve("./b.md"),
+            Resolution::Resolved("notes:sub/b.md".into())
+        );
+        assert_eq!(resolve("/a.md"), Resolution::Resolved("notes:a.md".into()));
+        assert_eq!(
+            resolve("../with%20space.md"),
+            Resolution::Resolved("notes:with space.md".into())
+        );
+        assert_eq!(
+            resolve("#section"),
+            Resolution::Resolved("notes:sub/b.md".into())
+        );
+        assert_eq!(resolve("c.md"), Resolution::Unresolved);
+        assert_eq!(resolve("../../a.md"), Resolution::Unresolved);
+    }
+
+    #[test]
+    fn resolve_cached_connections() -> Result<()> {
+        use crate::source::Source;
+        use crate::{extraction, sourcing};
+        use rusqlite::NO_PARAMS;
+        use std::fs;
+
+        let dir = TempDir::new("resolution")?;
+        fs::write(
+            dir.join("a.md"),
+            "[b](b.md), [self](notes:a.md), [c](c.md), <https://example.org> and [[beta]].",
+        )?;
+        fs::write(dir.join("b.md"), "+++\nid = \"beta\"\n+++\nNothing here.")?;
+
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        let source = Source::new("notes".parse()?, &dir);
+
+        sourcing::run(&conn, &source)?;
+        extraction::run(&conn, &Default::default())?;
+        let report = run(&conn)?;
+
+        let targets: Vec<Option<String>> = {
+            let mut stmt = conn.prepare("SELECT target_id FROM connection ORDER BY span_start")?;
+            let rows = stmt.query_map(NO_PARAMS, |row| row.get(0))?;
+            rows.collect::<std::result::Result<_, _>>()?
+        };
+
+        assert_eq!(report.resolved, 3);
+        assert_eq!(report.external, 1);
+        assert_eq!(report.unresolved.len(), 1);
+        assert_eq!(report.unresolved[0].target, "c.md");
+        assert_eq!(
+            targets,
+            vec![
+                Some("notes:b.md".into()),
+                Some("notes:a.md".into()),
+                None,
+                None,
+                Some("notes:b.md".into())
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn decode_percent_sequences() {
+        assert_eq!(percent_decode("a%20b%2Fc"), "a b/c");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%é"), "%zz%é");
+    }
+
+    fn resolve_wikilink(target: &str) -> Resolution {
+        index().resolve("notes", "sub/b.md", target, ConnectionType::Wikilink)
+    }
+
+    #[test]
+    fn wikilink_target() {
+        assert_eq!(
+            resolve_wikilink("gamma"),
+            Resolution::Resolved("wiki:c.md".into())
+        );
+        assert_eq!(
+            resolve_wikilink("Wiki Alpha#Section"),
+            Resolution::Resolved("wiki:a.md".into())
+        );
+        assert_eq!(
+            resolve_wikilink("c^block"),
+            Resolution::Resolved("wiki:c.md".into())
+        );
+        assert_eq!(
+            resolve_wikilink("sub/b"),
+            Resolution::Resolved("notes:sub/b.md".into())
+        );
+        assert_eq!(
+            resolve_wikilink("#Section"),
+            Resolution::Resolved("notes:sub/b.md".into())
+        );
+        assert_eq!(resolve_wikilink("nope"), Resolution::Unresolved);
+    }
+
+    #[test]
+    fn wikilink_prefers_origin_source() {
+        assert_eq!(
+            resolve_wikilink("a"),
+            Resolution::Resolved("notes:a.md".into())
+        );
+        assert_eq!(
+            resolve_wikilink("wiki:a"),
+            Resolution::Resolved("wiki:a.md".into())
+        );
+    }
+
+    #[test]
+    fn ambiguous_wikilink() {
+        assert_eq!(
+            resolve_wikilink("Duplicate"),
+            Resolution::Ambiguous(vec!["notes:other/dup.md".into(), "notes:sub/dup.md".into()])
+        );
+    }
+
+    #[test]
+    fn external_target() {
+        assert_eq!(resolve("https://example.org/a.md"), Resolution::External);
+        assert_eq!(resolve("mailto:foo@example.org"), Resolution::External);
+        assert_eq!(
+            index().resolve("notes", "a.md", "foo@example.org", ConnectionType::Autolink),
+            Resolution::External
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! This module is concerned with serving a built HTML site over HTTP for preview.
+//!
+//! HTML pages are served with a small script that polls the server for the number of rebuilds so
+//! far and reloads the page when it changes. The script is inlined so nothing is fetched from the
+//! network. The number of rebuilds guards the site: it is locked for writing while the site is
+//! rebuilt so pages are never served half written.
+
+use crate::context::Result;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use tiny_http::{Header, Request};
+
+/// The address served when none is given.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:4000";
+
+/// The URL polled by pages for the number of rebuilds.
+pub const RELOAD_URL: &str = "/_onelo/reload";
+
+/// The live reload script, where `{url}` is the reload URL and `{rebuilds}` the number of rebuilds
+/// the page was served at.
+const RELOAD_SCRIPT: &str = r#"<script>
+setInterval(function () {
+  fetch("{url}")
+    .then(function (response) { return response.text(); })
+    .then(function (rebuilds) { if (rebuilds !== "{rebuilds}") location.reload(); })
+    .catch(function () {});
+}, 1000);
+</script>
+"#;
+
+/// An HTTP server for the files of a directory.
+pub struct Server {
+    http: tiny_http::Server,
+    root: PathBuf,
+    rebuilds: Arc<RwLock<usize>>,
+}
+
+/// A response to a request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Server {
+    /// Listens on the given address to serve the files in `root`. Pages reload whenever the
+    /// `rebuilds` counter changes and requests wait while it is locked for writing.
+    pub fn bind<P: AsRef<Path>>(
+        address: &str,
+        root: P,
+        rebuilds: Arc<RwLock<usize>>,
+    ) -> Result<Self> {
+        let http =
+            tiny_http::Server::http(address).map_err(|err| format!("{}: {}", address, err))?;
+
+        Ok(Server {
+            http,
+            root: root.as_ref().to_path_buf(),
+            rebuilds,
+        })
+    }
+
+    /// The address the server listens on.
+    pub fn address(&self) -> SocketAddr {
+        self.http.server_addr()
+    }
+
+    /// Answers requests one at a time until the server fails.
+    pub fn run(&self) {
+        for request in self.http.incoming_requests() {
+            // A rebuild that panicked leaves the site as it was, still worth serving.
+            let rebuilds = self.rebuilds.read().unwrap_or_else(PoisonError::into_inner);
+            let response = respond(&self.root, *rebuilds, request.url());
+
+            drop(rebuilds);
+
+            if let Err(err) = send(request, response) {
+                eprintln!("{}", err);
+            }
+        }
+    }
+}
+
+fn send(request: Request, response: Response) -> Result<()> {
+    let header =
+        Header::from_bytes("Content-Type", response.content_type).map_err(|_| "Invalid header")?;
+
+    request.respond(
+        tiny_http::Response::from_data(response.body)
+            .with_status_code(response.status)
+            .with_header(header),
+    )?;
+
+    Ok(())
+}
+
+/// Builds the response to a request for the given URL.
+///
+/// Directories are answered with their `index.html` and paths leaving the root are refused.
+pub fn respond(root: &Path, rebuilds: usize, url: &str) -> Response {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+
+    if path == RELOAD_URL {
+        return Response {
+            status: 200,
+            content_type: "text/plain; charset=utf-8",
+            body: rebuilds.to_string().into_bytes(),
+        };
+    }
+
+    let not_found = Response {
+        status: 404,
+        content_type: "text/plain; charset=utf-8",
+        body: b"Not found".to_vec(),
+    };
+    let path = match decode(path.trim_start_matches('/')) {
+        Some(path) => PathBuf::from(path),
+        None => return not_found,
+    };
+
+    if path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return not_found;
+    }
+
+    let mut path = root.join(path);
+
+    if path.is_dir() {
+        path.push("index.html");
+    }
+
+    let body = match fs::read(&path) {
+        Ok(body) => body,
+        Err(_) => return not_found,
+    };
+    let content_type = content_type(&path);
+
+    if content_type.starts_with("text/html") {
+        return Response {
+            status: 200,
+            content_type,
+            body: inject(&String::from_utf8_lossy(&body), rebuilds).into_bytes(),
+        };
+    }
+
+    Response {
+        status: 200,
+        content_type,
+        body,
+    }
+}
+
+/// Adds the live reload script to a page, before the closing `body` tag if any.
+fn inject(html: &str, rebuilds: usize) -> String {
+    let script = RELOAD_SCRIPT
+        .replace("{url}", RELOAD_URL)
+        .replace("{rebuilds}", &rebuilds.to_string());
+
+    match html.rfind("</body>") {
+        Some(i) => format!("{}{}{}", &html[..i], script, &html[i..]),
+        None => format!("{}{}", html, script),
+    }
+}
+
+/// Guesses the content type of a file from its extension.
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("txt") | Some("md") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Decodes the percent-encoded characters of a URL path.
+fn decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = path.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+
+    #[test]
+    fn serve_files() -> Result<()> {
+        let dir = TempDir::new("serve")?;
+        fs::create_dir_all(dir.join("notes"))?;
+        fs::write(dir.join("index.html"), "<html><body>Index</body></html>")?;
+        fs::write(dir.join("notes/a b.html"), "<p>A</p>")?;
+        fs::write(dir.join("style.css"), "p {}")?;
+
+        let index = respond(&dir, 2, "/");
+        let page = respond(&dir, 2, "/notes/a%20b.html?x=1");
+        let style = respond(&dir, 2, "/style.css");
+        let reload = respond(&dir, 2, RELOAD_URL);
+        let missing = respond(&dir, 2, "/notes/c.html");
+        let outside = respond(&dir, 2, "/../index.html");
+
+        let index = String::from_utf8(index.body)?;
+        assert!(index.starts_with("<html><body>Index<script>"));
+        assert!(index.contains(r#"rebuilds !== "2""#));
+        assert!(index.ends_with("</script>\n</body></html>"));
+        assert_eq!(page.status, 200);
+        assert!(String::from_utf8(page.body)?.starts_with("<p>A</p><script>"));
+        assert_eq!(style.content_type, "text/css; charset=utf-8");
+        assert_eq!(style.body, b"p {}");
+        assert_eq!(reload.body, b"2");
+        assert_eq!(missing.status, 404);
+        assert_eq!(outside.status, 404);
+
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 //! This module is concerned with the data sources.
 
+use crate::filesystem::WalkOptions;
 use chrono::prelude::*;
 use std::error::Error;
 use std::fmt;
@@ -14,10 +15,13 @@ use std::str::FromStr;
 pub struct Source {
     id: Id,
     route: PathBuf,
-    // TODO: Should align with a Git commit hash. Consider how accommodating we
-    // can be with other VCS such as fossil or mercurial.
+    /// The Git revision to read the source at, if the route is a Git repository.
+    revision: Option<String>,
+    /// The hash of the Git commit the revision resolved to.
+    // TODO: Consider how accommodating we can be with other VCS such as fossil or mercurial.
     checksum: Option<Vec<u8>>,
     timestamp: DateTime<Utc>,
+    walk_options: WalkOptions,
 }
 
 impl Source {
@@ -25,27 +29,96 @@ impl Source {
         Source {
             id,
             route: PathBuf::from(route.as_ref()),
+            revision: None,
             checksum: None,
             timestamp: Utc::now(),
+            walk_options: WalkOptions::default(),
         }
     }
 
+    /// Sets the options deciding which files of the source are walked.
+    pub fn with_walk_options(mut self, walk_options: WalkOptions) -> Self {
+        self.walk_options = walk_options;
+        self
+    }
+
+    /// Reads the source from the Git repository at its route, at the given revision, e.g. a
+    /// branch, a tag or a commit hash.
+    pub fn with_revision<S: Into<String>>(mut self, revision: S) -> Self {
+        self.revision = Some(revision.into());
+        self
+    }
+
+    /// Sets the hash of the commit the source was read at.
+    pub fn with_checksum(mut self, checksum: Vec<u8>) -> Self {
+        self.checksum = Some(checksum);
+        self
+    }
+
     pub fn id(&self) -> &Id {
         &self.id
     }
+
+    pub fn route(&self) -> &Path {
+        &self.route
+    }
+
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
+    pub fn checksum(&self) -> Option<&[u8]> {
+        self.checksum.as_deref()
+    }
+
+    pub fn timestamp(&self) -> &DateTime<Utc> {
+        &self.timestamp
+    }
+
+    pub fn walk_options(&self) -> &WalkOptions {
+        &self.walk_options
+    }
 }
 
 /// A source identifier.
 ///
 /// A source identifier must obey certain rules such as not having a `:` in it.
+///
+/// Identifiers name directories in rendered sites, so they cannot be empty, contain a `/` or
+/// contain `..` either.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Id(String);
 
+impl Id {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl FromStr for Id {
     type Err = ParseIdError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseIdError(
+                "Source identifiers cannot be empty".to_string(),
+            ));
+        }
+
         guard_against_char(s, ':')?;
+        guard_against_char(s, '/')?;
+
+        if s.contains("..") {
+            return Err(ParseIdError(
+                "Source identifiers cannot have `..` in them".to_string(),
+            ));
+        }
 
         Ok(Id(s.into()))
     }
@@ -107,6 +180,13 @@ mod tests {
 
             assert!(input.is_err(), "Expects a string with `:`");
         }
+
+        #[test]
+        fn fails_outside_output() {
+            for input in &["", "a/b", "..", "../x"] {
+                assert!(Id::from_str(input).is_err(), "Expects {:?} to fail", input);
+            }
+        }
     }
 
     mod check_char {
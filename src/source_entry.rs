@@ -3,6 +3,7 @@
 use crate::checksum::Checksum;
 use crate::content_type::{ContentType, ContentTypeError};
 use crate::source::{Id as SourceId, ParseIdError};
+use chrono::prelude::*;
 use std::error::Error;
 use std::fmt;
 use std::str::FromStr;
@@ -10,16 +11,55 @@ use std::str::FromStr;
 /// A source entry.
 ///
 /// A source entry is the unprocessed information obtained from, for example, a file found in a
-/// source directory.
+/// source directory. Its identifier is qualified by its source, `source_id:path`, so entries with
+/// the same path in different sources don't collide.
 #[derive(Debug, PartialEq)]
 pub struct SourceEntry {
-    /// The local path to the original file.
     id: String,
     source_id: SourceId,
+    /// The path to the original file relative to the source route.
+    path: String,
     content_id: Option<Checksum>,
     content_type: ContentType,
 }
 
+impl SourceEntry {
+    pub fn new(
+        source_id: SourceId,
+        path: String,
+        content_id: Option<Checksum>,
+        content_type: ContentType,
+    ) -> Self {
+        SourceEntry {
+            id: id(&source_id, &path),
+            source_id,
+            path,
+            content_id,
+            content_type,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn source_id(&self) -> &SourceId {
+        &self.source_id
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn content_id(&self) -> Option<&Checksum> {
+        self.content_id.as_ref()
+    }
+
+    pub fn content_type(&self) -> &ContentType {
+        &self.content_type
+    }
+}
+
 impl FromStr for SourceEntry {
     type Err = SourceEntryError;
 
@@ -30,22 +70,41 @@ impl FromStr for SourceEntry {
             return Err(SourceEntryError::UnknownPattern);
         }
 
-        let id = pair[1].to_string();
+        let path = pair[1].to_string();
         let source_id = pair[0].parse()?;
-        let content_type = if let Some(ext) = id.rsplitn(2, '.').collect::<Vec<&str>>().first() {
+        let content_type = if let Some(ext) = path.rsplitn(2, '.').collect::<Vec<&str>>().first() {
             ContentType::from_extension(ext)?
         } else {
             return Err(SourceEntryError::MissingExtension);
         };
 
-        let entry = SourceEntry {
-            id,
-            source_id,
-            content_id: None,
-            content_type,
-        };
+        Ok(SourceEntry::new(source_id, path, None, content_type))
+    }
+}
+
+/// The history of a source entry as recorded by the version control system of its source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct History {
+    /// The time of the commit that added the entry.
+    pub created: DateTime<Utc>,
+    /// The time of the last commit that changed the entry.
+    pub updated: DateTime<Utc>,
+    /// The names of the authors of the commits that changed the entry, most recent first.
+    pub authors: Vec<String>,
+}
+
+/// Qualifies the path of a source entry with its source identifier.
+pub fn id(source_id: &SourceId, path: &str) -> String {
+    format!("{}:{}", source_id, path)
+}
+
+/// Returns the file name of a source entry path without its extension.
+pub fn stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
 
-        Ok(entry)
+    match name.rfind('.') {
+        Some(i) if i > 0 => &name[..i],
+        _ => name,
     }
 }
 
@@ -95,8 +154,9 @@ mod tests {
     #[test]
     fn new_source_entry() -> Result<(), SourceEntryError> {
         let expected = SourceEntry {
-            id: "foo.md".into(),
+            id: "unnamed:foo.md".into(),
             source_id: "unnamed".parse()?,
+            path: "foo.md".into(),
             content_id: None,
             content_type: ContentType::Markdown,
         };
@@ -109,19 +169,17 @@ mod tests {
     }
 
     #[test]
-    fn source_entry_empty_source() -> Result<(), SourceEntryError> {
-        let expected = SourceEntry {
-            id: "foo.md".into(),
-            source_id: "".parse()?,
-            content_id: None,
-            content_type: ContentType::Markdown,
-        };
-
-        let actual = SourceEntry::from_str(":foo.md")?;
+    fn source_entry_empty_source() {
+        let actual = SourceEntry::from_str(":foo.md");
 
-        assert_eq!(actual, expected);
+        assert!(actual.is_err(), "Expects an empty source id to be rejected");
+    }
 
-        Ok(())
+    #[test]
+    fn stem_of_path() {
+        assert_eq!(stem("subdir/a.md"), "a");
+        assert_eq!(stem("a"), "a");
+        assert_eq!(stem(".hidden"), ".hidden");
     }
 
     #[test]
@@ -0,0 +1,515 @@
+//! This module is concerned with the sourcing phase.
+//!
+//! Sourcing gathers every relevant entry from a source and stores it in the cache along with its
+//! content.
+//!
+//! Sourcing is incremental: entries whose size and modification time, or Git blob id, match the
+//! ones recorded in the cache are considered fresh and are neither read nor hashed again. The
+//! others are read and hashed on a pool of worker threads while the calling thread writes them to
+//! the cache. Each worker opens its own handle on a Git repository.
+
+use crate::archive;
+use crate::artefact::Content;
+use crate::cache;
+use crate::content_type::ContentType;
+use crate::context::Result;
+use crate::filesystem::{self, Stamp};
+use crate::front_matter::{self, FrontMatterError};
+use crate::git::Snapshot;
+use crate::source::Source;
+use crate::source_entry::{self, SourceEntry};
+use chrono::prelude::*;
+use git2::Repository;
+use rayon::prelude::*;
+use rusqlite::Connection;
+use std::fmt;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+/// The number of files read and hashed ahead of the cache writer.
+const READ_AHEAD: usize = 64;
+
+/// A summary of the changes found while sourcing.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Report {
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+    /// The files left out because their content type is unknown, e.g. `.txt` files.
+    pub skipped: usize,
+    /// The entries left out because their front matter is malformed.
+    pub invalid: Vec<Invalid>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} added, {} changed, {} removed, {} unchanged, {} skipped.",
+            self.added, self.changed, self.removed, self.unchanged, self.skipped
+        )
+    }
+}
+
+/// An entry left out of the cache because its front matter is malformed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invalid {
+    /// The location of the file as presented in errors.
+    pub location: String,
+    pub error: FrontMatterError,
+}
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.location, self.error)
+    }
+}
+
+/// Collects all entries from the given source into the cache.
+///
+/// A source with a revision is read from the Git repository at its route, at the commit the
+/// revision resolves to, and records the hash of that commit as its checksum along with the
+/// history of every entry. A source whose route is a `.tar`, `.tar.gz`, `.tgz` or `.zip` file is
+/// read from that archive.
+///
+/// Files of an unknown content type are skipped without being read. Entries no longer present in
+/// the source are removed from the cache, as well as any content left without entries. Entries
+/// with a malformed front matter are removed too and listed in the report so they are checked
+/// again on the next run.
+pub fn run(conn: &Connection, source: &Source) -> Result<Report> {
+    let archive = archive::Format::of(source.route()).filter(|_| source.route().is_file());
+
+    match (source.revision(), archive) {
+        (Some(revision), _) => {
+            let snapshot = Snapshot::open(source.route(), revision, source.walk_options())?;
+            let previous = cache::get_source_checksum(conn, source.id().as_str())?;
+            let source = source
+                .clone()
+                .with_checksum(snapshot.commit_id().as_bytes().to_vec());
+            let snapshot = &snapshot;
+            let files = snapshot.files().iter().map(|file| {
+                Ok(Found {
+                    location: format!("{}@{}:{}", source.route().display(), revision, file.path),
+                    path: file.path.clone(),
+                    // A blob has no time of its own and its id changes along with its content.
+                    stamp: Stamp {
+                        size: file.size,
+                        modified: Utc.timestamp(0, 0),
+                        digest: Some(file.blob_id.to_string()),
+                    },
+                    read: Box::new(move |worker| {
+                        Ok(snapshot.read(worker.repo(snapshot)?, file)?.into())
+                    }),
+                })
+            });
+
+            let report = store(conn, &source, files)?;
+
+            // The history of every entry stays the same as long as the commit does.
+            if report.added > 0
+                || previous.as_deref() != Some(snapshot.commit_id().to_string().as_str())
+            {
+                for (path, history) in snapshot.history()? {
+                    cache::insert_history(conn, &source_entry::id(source.id(), &path), &history)?;
+                }
+            }
+
+            Ok(report)
+        }
+        (None, Some(format)) => {
+            let cached = cache::get_source_entries(conn, source)?;
+            let files = archive::get_files(
+                source.route(),
+                format,
+                source.walk_options(),
+                |path, stamp| cached.get(path).is_some_and(|entry| entry.stamp == *stamp),
+            )?;
+
+            cache::delete_history(conn, source.id().as_str())?;
+
+            let files = files.into_iter().map(|file| {
+                let archive::File {
+                    path,
+                    stamp,
+                    content,
+                } = file;
+
+                Ok(Found {
+                    location: format!("{}:{}", source.route().display(), path),
+                    path,
+                    stamp,
+                    // Only fresh files are left unread and those are never read.
+                    read: Box::new(move |_| Ok(content.ok_or("Unread archive entry")?.into())),
+                })
+            });
+
+            store(conn, source, files)
+        }
+        (None, None) => {
+            let paths = filesystem::get_files(source.route(), source.walk_options())?;
+
+            cache::delete_history(conn, source.id().as_str())?;
+
+            let files = paths.iter().map(|path| {
+                Ok(Found {
+                    location: path.display().to_string(),
+                    path: filesystem::relative_path(path, source.route())?,
+                    stamp: filesystem::get_stamp(path)?,
+                    read: Box::new(move |_| filesystem::get_content(path)),
+                })
+            });
+
+            store(conn, source, files)
+        }
+    }
+}
+
+/// A file found in a source along with the means to read it.
+struct Found<'a> {
+    /// The path relative to the source route.
+    path: String,
+    /// The location of the file as presented in errors.
+    location: String,
+    stamp: Stamp,
+    read: Read<'a>,
+}
+
+/// Reads and hashes a file on a worker thread.
+type Read<'a> = Box<dyn FnOnce(&mut Worker) -> Result<Content> + Send + 'a>;
+
+/// What a thread reading files keeps from one file to the next.
+#[derive(Default)]
+struct Worker {
+    repo: Option<Repository>,
+}
+
+impl Worker {
+    /// The handle of the worker on the repository of the given snapshot, opened on first use.
+    fn repo(&mut self, snapshot: &Snapshot) -> Result<&Repository> {
+        let repo = match self.repo.take() {
+            Some(repo) => repo,
+            None => snapshot.repository()?,
+        };
+
+        Ok(self.repo.insert(repo))
+    }
+}
+
+fn store<'a, I>(conn: &Connection, source: &Source, files: I) -> Result<Report>
+where
+    I: Iterator<Item = Result<Found<'a>>>,
+{
+    cache::insert_source(conn, source)?;
+
+    let mut report = Report::default();
+    let mut cached = cache::get_source_entries(conn, source)?;
+    let mut stale = Vec::new();
+
+    for file in files {
+        let file = file?;
+        let content_type = match content_type(Path::new(&file.path)) {
+            Some(content_type) => content_type,
+            None => {
+                report.skipped += 1;
+                continue;
+            }
+        };
+
+        match cached.remove(&file.path) {
+            Some(previous) if previous.stamp == file.stamp => report.unchanged += 1,
+            previous => stale.push((
+                file,
+                content_type,
+                previous.map(|previous| previous.content_id),
+            )),
+        }
+    }
+
+    let (tx, rx) = mpsc::sync_channel(READ_AHEAD);
+
+    thread::scope(|scope| -> Result<()> {
+        scope.spawn(move || {
+            stale.into_par_iter().try_for_each_init(
+                || (tx.clone(), Worker::default()),
+                |(tx, worker), (file, content_type, previous)| {
+                    let Found {
+                        path,
+                        location,
+                        stamp,
+                        read,
+                    } = file;
+                    let content = read(worker).map_err(|err| format!("{}: {}", location, err));
+
+                    // Sending only fails once the writer gave up on an error.
+                    tx.send((path, location, stamp, content_type, previous, content))
+                        .map_err(drop)
+                },
+            )
+        });
+
+        for (path, location, stamp, content_type, previous, content) in rx {
+            let content = content?;
+            let document = if content_type == ContentType::Markdown {
+                let text = std::str::from_utf8(content.blob())
+                    .map_err(|err| format!("{}: {}", location, err))?;
+
+                match front_matter::parse(text) {
+                    Ok(document) => Some(document),
+                    Err(error) => {
+                        if previous.is_some() {
+                            cache::delete_source_entry(conn, source.id(), &path)?;
+                        }
+
+                        report.invalid.push(Invalid { location, error });
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+            let entry = SourceEntry::new(
+                source.id().clone(),
+                path,
+                Some(content.checksum().clone()),
+                content_type,
+            );
+
+            match previous {
+                None => report.added += 1,
+                Some(previous) if previous == content.checksum().to_string() => {
+                    report.unchanged += 1
+                }
+                Some(_) => report.changed += 1,
+            }
+
+            cache::insert_content_type(conn, entry.content_type())?;
+            cache::insert_content(conn, &content)?;
+            cache::insert_source_entry(conn, &entry, &stamp)?;
+
+            if let Some(document) = &document {
+                cache::insert_front_matter(conn, &entry, document)?;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    for path in cached.keys() {
+        cache::delete_source_entry(conn, source.id(), path)?;
+        report.removed += 1;
+    }
+
+    cache::delete_orphan_content(conn)?;
+
+    Ok(report)
+}
+
+/// Removes from the cache every source other than the given ones along with their entries.
+///
+/// Returns the number of entries removed.
+pub fn prune(conn: &Connection, sources: &[Source]) -> Result<usize> {
+    let mut removed = 0;
+
+    for source_id in cache::get_source_ids(conn)? {
+        if sources
+            .iter()
+            .any(|source| source.id().as_str() == source_id)
+        {
+            continue;
+        }
+
+        let source = Source::new(source_id.parse()?, "");
+
+        for path in cache::get_source_entries(conn, &source)?.keys() {
+            cache::delete_source_entry(conn, source.id(), path)?;
+            removed += 1;
+        }
+
+        cache::delete_source(conn, &source_id)?;
+    }
+
+    cache::delete_orphan_content(conn)?;
+
+    Ok(removed)
+}
+
+/// Casts the extension of the given path as a `ContentType`, if known.
+fn content_type(path: &Path) -> Option<ContentType> {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+
+    ContentType::from_extension(ext).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+    use crate::filesystem::WalkOptions;
+    use rusqlite::NO_PARAMS;
+    use std::fs;
+
+    #[test]
+    fn source_test_files() -> Result<()> {
+        let mut conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+
+        let tx = conn.transaction()?;
+        let source = Source::new("test".parse()?, "test/files");
+        let report = run(&tx, &source)?;
+        tx.commit()?;
+
+        let entries: i64 =
+            conn.query_row("SELECT count(*) FROM source_entry", NO_PARAMS, |row| {
+                row.get(0)
+            })?;
+        let contents: i64 =
+            conn.query_row("SELECT count(*) FROM content", NO_PARAMS, |row| row.get(0))?;
+
+        assert_eq!(report.added, 4);
+        assert_eq!(entries, 4);
+        // `02.md` and `subdir/c.md` are both empty so they share their content.
+        assert_eq!(contents, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn source_front_matter() -> Result<()> {
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        let source = Source::new("test".parse()?, "test/files");
+
+        run(&conn, &source)?;
+
+        let actual = cache::get_front_matter(&conn, "test:01.md")?.unwrap_or_default();
+        let format: Option<String> = conn.query_row(
+            "SELECT format FROM front_matter WHERE source_entry_id = 'test:01.md'",
+            NO_PARAMS,
+            |row| row.get(0),
+        )?;
+
+        assert_eq!(actual["id"].as_str(), Some("01"));
+        assert_eq!(format.as_deref(), Some("toml"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn source_invalid_utf8() -> Result<()> {
+        let dir = TempDir::new("sourcing-utf8")?;
+        fs::write(dir.join("a.md"), b"# A \xff")?;
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        let source = Source::new("test".parse()?, &dir);
+
+        let err = run(&conn, &source).map(|_| ()).unwrap_err();
+
+        assert!(err
+            .to_string()
+            .starts_with(&format!("{}: invalid utf-8", dir.join("a.md").display())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn source_incrementally() -> Result<()> {
+        let dir = TempDir::new("sourcing")?;
+        fs::write(dir.join("a.md"), "a")?;
+        fs::write(dir.join("b.md"), "b")?;
+        fs::write(dir.join("c.md"), "c")?;
+
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        let source = Source::new("test".parse()?, &dir);
+
+        let first = run(&conn, &source)?;
+
+        fs::write(dir.join("b.md"), "bb")?;
+        fs::remove_file(dir.join("c.md"))?;
+        fs::write(dir.join("d.md"), "d")?;
+
+        let second = run(&conn, &source)?;
+        let contents: i64 =
+            conn.query_row("SELECT count(*) FROM content", NO_PARAMS, |row| row.get(0))?;
+
+        assert_eq!(first.added, 3);
+        assert_eq!(
+            second,
+            Report {
+                added: 1,
+                changed: 1,
+                removed: 1,
+                unchanged: 1,
+                skipped: 0,
+                invalid: Vec::new(),
+            }
+        );
+        assert_eq!(contents, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn source_git_revision() -> Result<()> {
+        let dir = TempDir::new("sourcing-git")?;
+        let repo = git2::Repository::init(&dir)?;
+        let first = crate::git::tests::commit(&repo, &[("a.md", "# A")], "First")?;
+        crate::git::tests::commit(&repo, &[("b.md", "# B")], "Second")?;
+
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        let source = Source::new("test".parse()?, &dir).with_revision(first.to_string());
+
+        let report = run(&conn, &source)?;
+        let checksum: Option<String> =
+            conn.query_row("SELECT checksum FROM source", NO_PARAMS, |row| row.get(0))?;
+        let histories: i64 =
+            conn.query_row("SELECT count(*) FROM history", NO_PARAMS, |row| row.get(0))?;
+        let before = cache::get_source_entries(&conn, &source)?;
+
+        let head = Source::new("test".parse()?, &dir).with_revision("HEAD");
+        let second = run(&conn, &head)?;
+        let after = cache::get_source_entries(&conn, &head)?;
+
+        assert_eq!(report.added, 1);
+        assert_eq!(checksum, Some(first.to_string()));
+        assert_eq!(histories, 1);
+        // A file left untouched by a commit keeps its stamp so it is not read again.
+        assert_eq!(second.added, 1);
+        assert_eq!(second.unchanged, 1);
+        assert_eq!(before["a.md"].stamp, after["a.md"].stamp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn source_skips_unknown_content_types() -> Result<()> {
+        let dir = TempDir::new("sourcing-unknown")?;
+        fs::write(dir.join("a.md"), "# A")?;
+        fs::write(dir.join("b.txt"), "B")?;
+
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        let source = Source::new("test".parse()?, &dir).with_walk_options(WalkOptions {
+            include: vec!["*.md".to_string(), "*.txt".to_string()],
+            ..WalkOptions::default()
+        });
+
+        let report = run(&conn, &source)?;
+        let entries: i64 =
+            conn.query_row("SELECT count(*) FROM source_entry", NO_PARAMS, |row| {
+                row.get(0)
+            })?;
+
+        assert_eq!(report.added, 1);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(entries, 1);
+
+        Ok(())
+    }
+}
@@ -25,11 +25,21 @@ CREATE TABLE IF NOT EXISTS source (
 
 -- The set of source entries found in the processed sources.
 CREATE TABLE IF NOT EXISTS source_entry (
+    -- The path qualified by the source identifier, `source_id:path`.
     id              text NOT NULL PRIMARY KEY,
     source_id       text NOT NULL,
+    -- The path relative to the source route, using `/` as separator.
+    path            text NOT NULL,
     content_id      text NOT NULL,
     content_type_id text,
+    -- File size and modification time, used to detect stale entries without
+    -- reading their content.
+    size            integer NOT NULL,
+    modified        datetime NOT NULL,
+    -- A hash of the content given by the source, e.g. the Git blob id.
+    digest          text,
 
+    UNIQUE (source_id, path),
     FOREIGN KEY (source_id) REFERENCES source(id),
     FOREIGN KEY (content_type_id) REFERENCES content_type(id),
     FOREIGN KEY (content_id) REFERENCES content(id)
@@ -41,3 +51,83 @@ CREATE TABLE IF NOT EXISTS content (
     id              text NOT NULL PRIMARY KEY,
     content         blob NOT NULL
 );
+
+-- The history of the source entries of Git sources.
+CREATE TABLE IF NOT EXISTS history (
+    source_entry_id text NOT NULL PRIMARY KEY,
+    -- The time of the commit that added the entry.
+    created         datetime NOT NULL,
+    -- The time of the last commit that changed the entry.
+    updated         datetime NOT NULL,
+    -- The names of the authors as a JSON array, most recent first.
+    authors         text NOT NULL,
+
+    FOREIGN KEY (source_entry_id) REFERENCES source_entry(id)
+);
+
+-- The metadata found in the front matter of source entries, as a JSON object.
+CREATE TABLE IF NOT EXISTS front_matter (
+    source_entry_id text NOT NULL PRIMARY KEY,
+    -- The original format (toml, yaml or json), if the entry has a front
+    -- matter at all.
+    format          text,
+    metadata        text NOT NULL,
+
+    FOREIGN KEY (source_entry_id) REFERENCES source_entry(id)
+);
+
+-- The set of nodes of the graph. A node shares its identifier with the source
+-- entry it was extracted from.
+CREATE TABLE IF NOT EXISTS node (
+    id         text NOT NULL PRIMARY KEY,
+    title      text NOT NULL,
+    -- The content the node was extracted from. A node is stale when it
+    -- differs from the source entry content.
+    content_id text NOT NULL,
+
+    FOREIGN KEY (id) REFERENCES source_entry(id)
+);
+
+-- The set of known connection types.
+CREATE TABLE IF NOT EXISTS connection_type (
+    id    text NOT NULL PRIMARY KEY,
+    label text NOT NULL
+);
+
+INSERT OR IGNORE INTO connection_type (id, label) VALUES
+    ('inline', 'Inline link'),
+    ('reference', 'Reference link'),
+    ('autolink', 'Autolink'),
+    ('wikilink', 'Wikilink');
+
+-- The set of directed connections found in the content of nodes.
+CREATE TABLE IF NOT EXISTS connection (
+    origin_id          text NOT NULL,
+    -- The target as written in the origin content, e.g. a link href.
+    target             text NOT NULL,
+    -- The node the target resolves to, if any.
+    target_id          text,
+    -- The text of the connection, e.g. a link text.
+    title              text NOT NULL,
+    connection_type_id text NOT NULL,
+    -- The byte span of the connection in the origin content.
+    span_start         integer NOT NULL,
+    span_end           integer NOT NULL,
+
+    -- A link and a wikilink may start at the same offset, e.g. `[[a]](b)`.
+    PRIMARY KEY (origin_id, span_start, connection_type_id),
+    FOREIGN KEY (origin_id) REFERENCES node(id),
+    FOREIGN KEY (target_id) REFERENCES node(id),
+    FOREIGN KEY (connection_type_id) REFERENCES connection_type(id)
+);
+
+-- The set of files written by the HTML output. A file is rendered again when
+-- the checksum of the data it was rendered from changes or when its template
+-- changes.
+CREATE TABLE IF NOT EXISTS output (
+    -- The path of the file relative to the output directory.
+    id          text NOT NULL PRIMARY KEY,
+    template_id text NOT NULL,
+    -- The checksum of the data the file was rendered from.
+    checksum    text NOT NULL
+);
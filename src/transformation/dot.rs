@@ -0,0 +1,117 @@
+//! This module is concerned with rendering the graph as a Graphviz DOT file.
+//!
+//! Nodes are labelled with their front matter `label` or their title and edges with their
+//! connection type. When clustering, the nodes of each source are grouped in a subgraph.
+
+use super::Options;
+use crate::graph::Graph;
+use std::fmt::Write;
+
+/// Renders the graph as a DOT digraph.
+pub fn render(graph: &Graph, options: &Options) -> String {
+    let mut out = String::from("digraph onelo {\n");
+
+    if options.cluster {
+        for (i, source_id) in graph.source_ids().into_iter().enumerate() {
+            writeln!(out, "  subgraph cluster_{} {{", i).unwrap();
+            writeln!(out, "    label={};", quote(source_id)).unwrap();
+
+            for node in graph.nodes.iter().filter(|n| n.source_id == source_id) {
+                writeln!(
+                    out,
+                    "    {} [label={}];",
+                    quote(&node.id),
+                    quote(node.label())
+                )
+                .unwrap();
+            }
+
+            out.push_str("  }\n");
+        }
+    } else {
+        for node in &graph.nodes {
+            writeln!(
+                out,
+                "  {} [label={}];",
+                quote(&node.id),
+                quote(node.label())
+            )
+            .unwrap();
+        }
+    }
+
+    for edge in &graph.edges {
+        writeln!(
+            out,
+            "  {} -> {} [label={}];",
+            quote(&edge.origin_id),
+            quote(&edge.target_id),
+            quote(edge.connection_type.as_str())
+        )
+        .unwrap();
+    }
+
+    out.push_str("}\n");
+
+    out
+}
+
+/// Quotes a DOT identifier.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionType;
+    use crate::front_matter::Value;
+    use crate::graph::tests::node;
+    use crate::graph::Edge;
+
+    #[test]
+    fn render_dot() {
+        let graph = Graph {
+            nodes: vec![
+                node("a.md", "x", "Say \"a\""),
+                node("b.md", "y", "Beta").with("label", Value::String("B".to_string())),
+            ],
+            edges: vec![Edge {
+                origin_id: "a.md".to_string(),
+                target_id: "b.md".to_string(),
+                connection_type: ConnectionType::Wikilink,
+                title: "b".to_string(),
+                span: 0..0,
+            }],
+        };
+
+        assert_eq!(
+            render(&graph, &Options::default()),
+            "digraph onelo {\n  \"a.md\" [label=\"Say \\\"a\\\"\"];\n  \"b.md\" [label=\"B\"];\n  \"a.md\" -> \"b.md\" [label=\"wikilink\"];\n}\n"
+        );
+        assert_eq!(
+            render(
+                &graph,
+                &Options {
+                    cluster: true,
+                    ..Options::default()
+                }
+            ),
+            "digraph onelo {\n  subgraph cluster_0 {\n    label=\"x\";\n    \"a.md\" [label=\"Say \\\"a\\\"\"];\n  }\n  subgraph cluster_1 {\n    label=\"y\";\n    \"b.md\" [label=\"B\"];\n  }\n  \"a.md\" -> \"b.md\" [label=\"wikilink\"];\n}\n"
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! This module is concerned with rendering the graph as a GEXF 1.2 document.
+//!
+//! Nodes are labelled with their title and carry their source, path and checksum along with every
+//! front matter key as a typed attribute. Edges are labelled with their text and carry their
+//! connection type.
+
+use super::{attribute_value, attributes, escape_xml, AttributeType};
+use crate::graph::Graph;
+use std::fmt::Write;
+
+/// The attributes every node has.
+const NODE_ATTRIBUTES: [&str; 3] = ["source", "path", "checksum"];
+
+/// Renders the graph as a GEXF document.
+pub fn render(graph: &Graph) -> String {
+    let attributes = attributes(graph);
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gexf xmlns=\"http://www.gexf.net/1.2draft\" version=\"1.2\">\n  <graph mode=\"static\" defaultedgetype=\"directed\">\n",
+    );
+
+    out.push_str("    <attributes class=\"node\">\n");
+
+    for (i, name) in NODE_ATTRIBUTES.iter().enumerate() {
+        attribute(&mut out, i, name, AttributeType::String);
+    }
+
+    for (i, (name, kind)) in attributes.iter().enumerate() {
+        attribute(&mut out, NODE_ATTRIBUTES.len() + i, name, *kind);
+    }
+
+    out.push_str("    </attributes>\n    <attributes class=\"edge\">\n");
+    attribute(&mut out, 0, "type", AttributeType::String);
+    out.push_str("    </attributes>\n    <nodes>\n");
+
+    for node in &graph.nodes {
+        writeln!(
+            out,
+            "      <node id=\"{}\" label=\"{}\">\n        <attvalues>",
+            escape_xml(&node.id),
+            escape_xml(&node.title)
+        )
+        .unwrap();
+
+        for (i, value) in [&node.source_id, &node.path, &node.content_id]
+            .iter()
+            .enumerate()
+        {
+            attvalue(&mut out, i, value);
+        }
+
+        for (i, (name, _)) in attributes.iter().enumerate() {
+            if let Some(value) = node.metadata.get(name).and_then(attribute_value) {
+                attvalue(&mut out, NODE_ATTRIBUTES.len() + i, &value);
+            }
+        }
+
+        out.push_str("        </attvalues>\n      </node>\n");
+    }
+
+    out.push_str("    </nodes>\n    <edges>\n");
+
+    for (i, edge) in graph.edges.iter().enumerate() {
+        writeln!(
+            out,
+            "      <edge id=\"{}\" source=\"{}\" target=\"{}\" label=\"{}\">\n        <attvalues>",
+            i,
+            escape_xml(&edge.origin_id),
+            escape_xml(&edge.target_id),
+            escape_xml(&edge.title)
+        )
+        .unwrap();
+        attvalue(&mut out, 0, edge.connection_type.as_str());
+        out.push_str("        </attvalues>\n      </edge>\n");
+    }
+
+    out.push_str("    </edges>\n  </graph>\n</gexf>\n");
+
+    out
+}
+
+fn attribute(out: &mut String, id: usize, name: &str, kind: AttributeType) {
+    writeln!(
+        out,
+        "      <attribute id=\"{}\" title=\"{}\" type=\"{}\"/>",
+        id,
+        escape_xml(name),
+        kind.as_str()
+    )
+    .unwrap();
+}
+
+fn attvalue(out: &mut String, id: usize, value: &str) {
+    writeln!(
+        out,
+        "          <attvalue for=\"{}\" value=\"{}\"/>",
+        id,
+        escape_xml(value)
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionType;
+    use crate::front_matter::Value;
+    use crate::graph::tests::node;
+    use crate::graph::Edge;
+
+    #[test]
+    fn render_gexf() {
+        let graph = Graph {
+            nodes: vec![node("a.md", "x", "\"A\"").with("draft", Value::Boolean(false))],
+            edges: vec![Edge {
+                origin_id: "a.md".to_string(),
+                target_id: "a.md".to_string(),
+                connection_type: ConnectionType::Wikilink,
+                title: "a".to_string(),
+                span: 0..0,
+            }],
+        };
+        let actual = render(&graph);
+
+        assert!(actual.contains("      <attribute id=\"3\" title=\"draft\" type=\"boolean\"/>\n"));
+        assert!(actual.contains(
+            "      <node id=\"a.md\" label=\"&quot;A&quot;\">\n        <attvalues>\n          <attvalue for=\"0\" value=\"x\"/>\n          <attvalue for=\"1\" value=\"a.md\"/>\n          <attvalue for=\"2\" value=\"1e20\"/>\n          <attvalue for=\"3\" value=\"false\"/>\n        </attvalues>\n      </node>\n"
+        ));
+        assert!(actual.contains(
+            "      <edge id=\"0\" source=\"a.md\" target=\"a.md\" label=\"a\">\n        <attvalues>\n          <attvalue for=\"0\" value=\"wikilink\"/>\n"
+        ));
+    }
+}
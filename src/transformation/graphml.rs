@@ -0,0 +1,133 @@
+//! This module is concerned with rendering the graph as a GraphML document.
+//!
+//! Nodes carry their source, path, title and checksum along with every front matter key as a
+//! typed attribute. Edges carry their connection type and label.
+
+use super::{attribute_value, attributes, escape_xml};
+use crate::graph::Graph;
+use std::fmt::Write;
+
+/// The attributes every node has.
+const NODE_KEYS: [&str; 4] = ["source", "path", "title", "checksum"];
+
+/// The attributes every edge has.
+const EDGE_KEYS: [&str; 2] = ["type", "label"];
+
+/// Renders the graph as a GraphML document.
+pub fn render(graph: &Graph) -> String {
+    let attributes = attributes(graph);
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+    );
+
+    for key in &NODE_KEYS {
+        writeln!(
+            out,
+            "  <key id=\"{0}\" for=\"node\" attr.name=\"{0}\" attr.type=\"string\"/>",
+            key
+        )
+        .unwrap();
+    }
+
+    for (i, (name, kind)) in attributes.iter().enumerate() {
+        writeln!(
+            out,
+            "  <key id=\"m{}\" for=\"node\" attr.name=\"{}\" attr.type=\"{}\"/>",
+            i,
+            escape_xml(name),
+            kind.as_str()
+        )
+        .unwrap();
+    }
+
+    for key in &EDGE_KEYS {
+        writeln!(
+            out,
+            "  <key id=\"{0}\" for=\"edge\" attr.name=\"{0}\" attr.type=\"string\"/>",
+            key
+        )
+        .unwrap();
+    }
+
+    out.push_str("  <graph id=\"onelo\" edgedefault=\"directed\">\n");
+
+    for node in &graph.nodes {
+        writeln!(out, "    <node id=\"{}\">", escape_xml(&node.id)).unwrap();
+
+        for (key, value) in
+            NODE_KEYS
+                .iter()
+                .zip(&[&node.source_id, &node.path, &node.title, &node.content_id])
+        {
+            data(&mut out, key, value);
+        }
+
+        for (i, (name, _)) in attributes.iter().enumerate() {
+            if let Some(value) = node.metadata.get(name).and_then(attribute_value) {
+                data(&mut out, &format!("m{}", i), &value);
+            }
+        }
+
+        out.push_str("    </node>\n");
+    }
+
+    for edge in &graph.edges {
+        writeln!(
+            out,
+            "    <edge source=\"{}\" target=\"{}\">",
+            escape_xml(&edge.origin_id),
+            escape_xml(&edge.target_id)
+        )
+        .unwrap();
+        data(&mut out, "type", edge.connection_type.as_str());
+        data(&mut out, "label", &edge.title);
+        out.push_str("    </edge>\n");
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+
+    out
+}
+
+fn data(out: &mut String, key: &str, value: &str) {
+    writeln!(
+        out,
+        "      <data key=\"{}\">{}</data>",
+        key,
+        escape_xml(value)
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionType;
+    use crate::front_matter::Value;
+    use crate::graph::tests::node;
+    use crate::graph::Edge;
+
+    #[test]
+    fn render_graphml() {
+        let graph = Graph {
+            nodes: vec![node("a.md", "x", "A & B").with("rank", Value::Integer(3))],
+            edges: vec![Edge {
+                origin_id: "a.md".to_string(),
+                target_id: "a.md".to_string(),
+                connection_type: ConnectionType::Inline,
+                title: "self".to_string(),
+                span: 0..0,
+            }],
+        };
+        let actual = render(&graph);
+
+        assert!(actual
+            .contains("  <key id=\"m0\" for=\"node\" attr.name=\"rank\" attr.type=\"long\"/>\n"));
+        assert!(actual.contains(
+            "    <node id=\"a.md\">\n      <data key=\"source\">x</data>\n      <data key=\"path\">a.md</data>\n      <data key=\"title\">A &amp; B</data>\n      <data key=\"checksum\">1e20</data>\n      <data key=\"m0\">3</data>\n    </node>\n"
+        ));
+        assert!(actual.contains(
+            "    <edge source=\"a.md\" target=\"a.md\">\n      <data key=\"type\">inline</data>\n      <data key=\"label\">self</data>\n    </edge>\n"
+        ));
+    }
+}
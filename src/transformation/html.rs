@@ -0,0 +1,597 @@
+//! This module is concerned with rendering the graph as a static HTML site.
+//!
+//! Every node becomes a page at `<source_id>/<path>.html` where links and wikilinks resolved to a
+//! node point to that node's page and a backlinks section lists the pages linking to it. Every
+//! tag found in the front matter `tags` gets a page at `tags/<tag>.html` and an `index.html` page
+//! lists every page. Pages are rendered with the templates described in `templates`.
+//!
+//! Pages of a previous run that are no longer part of the site are removed, and two pages sharing
+//! a URL are reported as an error rather than overwriting each other.
+
+use super::templates::{Templates, INDEX, LAYOUT, NODE, TAG};
+use super::Report;
+use crate::cache;
+use crate::checksum::Checksum;
+use crate::connection::ConnectionType;
+use crate::context::Result;
+use crate::front_matter::{self, Value};
+use crate::graph::{Edge, Graph, GraphNode};
+use crate::markdown;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use rusqlite::Connection;
+use serde_json::json;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The characters percent-encoded in a segment of a URL path.
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'\'')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Writes a page per node, a page per tag and the index page into the given directory.
+///
+/// Pages whose data and template are unchanged since the last run are not rendered again.
+pub fn write(
+    conn: &Connection,
+    graph: &Graph,
+    templates: &Templates,
+    output_path: &Path,
+) -> Result<Report> {
+    let nodes: HashMap<&str, &GraphNode> = graph.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let tags = tags(graph);
+    let mut outgoing: HashMap<&str, Vec<(&Edge, &GraphNode)>> = HashMap::new();
+    let mut incoming: HashMap<&str, Vec<&GraphNode>> = HashMap::new();
+    let mut linked: HashSet<(&str, &str)> = HashSet::new();
+    let mut site = Site {
+        conn,
+        templates,
+        output_path,
+        urls: HashSet::new(),
+        report: Report::default(),
+    };
+
+    check_urls(graph, &tags)?;
+
+    for edge in &graph.edges {
+        let origin = nodes.get(edge.origin_id.as_str());
+        let target = nodes.get(edge.target_id.as_str());
+
+        if let (Some(origin), Some(target)) = (origin, target) {
+            outgoing.entry(&origin.id).or_default().push((edge, target));
+
+            if linked.insert((&origin.id, &target.id)) {
+                incoming.entry(&target.id).or_default().push(origin);
+            }
+        }
+    }
+
+    invalidate(conn, templates)?;
+
+    for node in &graph.nodes {
+        let url = page_path(node);
+        let blob = cache::get_content(conn, &node.content_id)?;
+        let text = String::from_utf8(blob)?;
+        let document = front_matter::parse(&text).map_err(|err| format!("{}:{}", node.id, err))?;
+        let edges = outgoing
+            .get(node.id.as_str())
+            .map_or(&[][..], Vec::as_slice);
+
+        let targets: HashMap<(usize, ConnectionType), &GraphNode> = edges
+            .iter()
+            .map(|(edge, target)| ((edge.span.start, edge.connection_type), *target))
+            .collect();
+        let body = markdown::to_html(document.body, |link| {
+            let start = document.body_offset + link.span.start;
+            let target = targets.get(&(start, link.connection_type))?;
+            let mut href = relative_url(&url, &page_path(target));
+
+            if link.connection_type != ConnectionType::Wikilink {
+                if let Some(i) = link.href.find('#') {
+                    href.push_str(&link.href[i..]);
+                }
+            }
+
+            Some(href)
+        });
+        let links: Vec<serde_json::Value> = edges
+            .iter()
+            .map(|(edge, target)| {
+                json!({
+                    "url": relative_url(&url, &page_path(target)),
+                    "title": target.title,
+                    "type": edge.connection_type.as_str(),
+                    "label": edge.title,
+                })
+            })
+            .collect();
+        let backlinks = incoming
+            .get(node.id.as_str())
+            .map_or(&[][..], Vec::as_slice);
+        let node_tags: Vec<serde_json::Value> = node_tags(node)
+            .iter()
+            .map(|tag| json!({ "name": tag, "url": relative_url(&url, &tag_path(tag)) }))
+            .collect();
+        let metadata: serde_json::Map<String, serde_json::Value> = node
+            .metadata
+            .iter()
+            .map(|(k, v)| (k.clone(), v.into()))
+            .collect();
+
+        site.page(
+            &url,
+            NODE,
+            json!({
+                "title": node.title,
+                "root": root(&url),
+                "node": {
+                    "id": node.id,
+                    "source": node.source_id,
+                    "path": node.path,
+                    "title": node.title,
+                    "url": relative_url(&url, &url),
+                    "metadata": metadata,
+                    "checksum": node.content_id,
+                    "history": node.history.as_ref().map(|history| json!({
+                        "created": history.created.to_rfc3339(),
+                        "updated": history.updated.to_rfc3339(),
+                        "authors": history.authors,
+                    })),
+                },
+                "body": body,
+                "links": links,
+                "backlinks": list(&url, backlinks),
+                "tags": node_tags,
+            }),
+        )?;
+    }
+
+    for (tag, nodes) in &tags {
+        let url = tag_path(tag);
+
+        site.page(
+            &url,
+            TAG,
+            json!({
+                "title": tag,
+                "root": root(&url),
+                "tag": tag,
+                "nodes": list(&url, nodes),
+            }),
+        )?;
+    }
+
+    let mut index: Vec<&GraphNode> = graph.nodes.iter().collect();
+    index.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+    let index_tags: Vec<serde_json::Value> = tags
+        .keys()
+        .map(|tag| json!({ "name": tag, "url": relative_url("index.html", &tag_path(tag)) }))
+        .collect();
+
+    site.page(
+        "index.html",
+        INDEX,
+        json!({
+            "title": "Index",
+            "root": "",
+            "nodes": list("index.html", &index),
+            "tags": index_tags,
+        }),
+    )?;
+    site.clean()?;
+
+    Ok(site.report)
+}
+
+/// Makes sure no two pages share a URL, e.g. `a.md` and `a.markdown`, the tags `C++` and `C--`
+/// or a tag and a page of a source named `tags`.
+fn check_urls(graph: &Graph, tags: &BTreeMap<String, Vec<&GraphNode>>) -> Result<()> {
+    let mut owners: HashMap<String, String> = HashMap::new();
+    let pages = graph
+        .nodes
+        .iter()
+        .map(|node| (page_path(node), format!("`{}`", node.id)))
+        .chain(
+            tags.keys()
+                .map(|tag| (tag_path(tag), format!("tag `{}`", tag))),
+        );
+
+    for (url, owner) in pages {
+        match owners.entry(url) {
+            Entry::Occupied(entry) => {
+                return Err(format!(
+                    "{} and {} are both rendered to {}",
+                    entry.get(),
+                    owner,
+                    entry.key()
+                )
+                .into())
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(owner);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Forgets the pages rendered with a template that changed since the last run. A change in the
+/// layout affects every page.
+fn invalidate(conn: &Connection, templates: &Templates) -> Result<()> {
+    for (name, checksum) in templates.checksums() {
+        let key = format!("template:{}", name);
+        let checksum = checksum.to_string();
+
+        if cache::get_context_value(conn, &key)?.as_deref() != Some(checksum.as_str()) {
+            let template_id = if *name == LAYOUT { None } else { Some(*name) };
+
+            cache::invalidate_outputs(conn, template_id)?;
+            cache::insert_context_value(conn, &key, &checksum)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The destination of the pages along with the outcome so far.
+struct Site<'a> {
+    conn: &'a Connection,
+    templates: &'a Templates,
+    output_path: &'a Path,
+    /// The pages of the current run, rendered or not.
+    urls: HashSet<String>,
+    report: Report,
+}
+
+impl<'a> Site<'a> {
+    /// Renders a page unless it was already rendered from the same data.
+    fn page(&mut self, url: &str, template: &str, data: serde_json::Value) -> Result<()> {
+        let checksum = Checksum::new(data.to_string().as_bytes()).to_string();
+        let path = self.output_path.join(url);
+
+        self.urls.insert(url.to_string());
+
+        if path.exists()
+            && cache::get_output_checksum(self.conn, url)?.as_deref() == Some(checksum.as_str())
+        {
+            self.report.unchanged += 1;
+
+            return Ok(());
+        }
+
+        let html = self.templates.render(template, &data)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, html)?;
+        cache::insert_output(self.conn, url, template, &checksum)?;
+        self.report.written += 1;
+
+        Ok(())
+    }
+
+    /// Removes the pages of a previous run that are no longer part of the site, e.g. the page of
+    /// a deleted node.
+    fn clean(&mut self) -> Result<()> {
+        for url in cache::get_output_ids(self.conn)? {
+            if self.urls.contains(&url) {
+                continue;
+            }
+
+            match fs::remove_file(self.output_path.join(&url)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+
+            cache::delete_output(self.conn, &url)?;
+            self.report.removed += 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// Groups the nodes by the tags found in their front matter `tags`.
+fn tags(graph: &Graph) -> BTreeMap<String, Vec<&GraphNode>> {
+    let mut tags: BTreeMap<String, Vec<&GraphNode>> = BTreeMap::new();
+
+    for node in &graph.nodes {
+        for tag in node_tags(node) {
+            tags.entry(tag).or_default().push(node);
+        }
+    }
+
+    tags
+}
+
+/// The tags of a node, either a list of strings or a single string.
+fn node_tags(node: &GraphNode) -> Vec<String> {
+    match node.metadata.get("tags") {
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(|value| value.as_str())
+            .map(String::from)
+            .collect(),
+        Some(Value::String(tag)) => vec![tag.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// The path of the page of a tag relative to the root of the site.
+pub fn tag_path(tag: &str) -> String {
+    let slug: String = tag
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+
+    format!("tags/{}.html", slug)
+}
+
+/// The relative path from a page to the root of the site.
+fn root(url: &str) -> String {
+    "../".repeat(url.matches('/').count())
+}
+
+/// The path of the page of a node relative to the root of the site.
+///
+/// The path is kept as is on disk; links to it are percent-encoded by `relative_url`.
+pub fn page_path(node: &GraphNode) -> String {
+    let path = match node.path.rfind('.') {
+        Some(i) if !node.path[i..].contains('/') => &node.path[..i],
+        _ => &node.path,
+    };
+
+    format!("{}/{}.html", node.source_id, path)
+}
+
+/// Computes the URL of a page relative to another page, both given as paths relative to the root
+/// of the site, percent-encoding every segment.
+pub fn relative_url(from: &str, to: &str) -> String {
+    let from: Vec<&str> = from.split('/').collect();
+    let to: Vec<&str> = to.split('/').collect();
+    let from_dirs = &from[..from.len() - 1];
+    let to_dirs = &to[..to.len() - 1];
+    let common = from_dirs
+        .iter()
+        .zip(to_dirs)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut url = "../".repeat(from_dirs.len() - common);
+
+    let segments: Vec<String> = to[common..]
+        .iter()
+        .map(|segment| utf8_percent_encode(segment, SEGMENT).to_string())
+        .collect();
+
+    url.push_str(&segments.join("/"));
+
+    url
+}
+
+/// Lists the URL and title of the pages of the given nodes.
+fn list(from: &str, nodes: &[&GraphNode]) -> Vec<serde_json::Value> {
+    nodes
+        .iter()
+        .map(|node| {
+            json!({
+                "url": relative_url(from, &page_path(node)),
+                "title": node.title,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+    use crate::source::Source;
+    use crate::{extraction, graph, resolution, sourcing};
+
+    #[test]
+    fn relative_urls() {
+        assert_eq!(relative_url("x/a.html", "x/b.html"), "b.html");
+        assert_eq!(relative_url("x/d/a.html", "x/b.html"), "../b.html");
+        assert_eq!(relative_url("x/a.html", "y/d/b.html"), "../y/d/b.html");
+        assert_eq!(relative_url("index.html", "x/b.html"), "x/b.html");
+        assert_eq!(
+            relative_url("x/a.html", "x/b c#d?.html"),
+            "b%20c%23d%3F.html"
+        );
+        assert_eq!(relative_url("x/a.html", "x/100%.html"), "100%25.html");
+    }
+
+    #[test]
+    fn write_site() -> Result<()> {
+        let dir = TempDir::new("html")?;
+        let input = dir.join("input");
+        let output = dir.join("output");
+        let templates_path = dir.join("templates");
+        fs::create_dir_all(&input)?;
+        fs::create_dir_all(&templates_path)?;
+        fs::write(
+            input.join("a.md"),
+            "+++\ntitle = \"Alpha\"\ntags = [\"Greek letters\"]\n+++\nSee [b](b.md#top), [[b|Beta]] and [[missing]].\n",
+        )?;
+        fs::write(input.join("b.md"), "# Beta\n")?;
+
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        let source = Source::new("test".parse()?, &input);
+
+        sourcing::run(&conn, &source)?;
+        extraction::run(&conn, &Default::default())?;
+        resolution::run(&conn)?;
+        let graph = graph::load(&conn)?;
+        let first = write(&conn, &graph, &Templates::load(None)?, &output)?;
+        let second = write(&conn, &graph, &Templates::load(None)?, &output)?;
+        fs::write(
+            templates_path.join(TAG),
+            "{% extends \"layout.html\" %}{% block content %}#{{ tag }}{% endblock %}",
+        )?;
+        let third = write(
+            &conn,
+            &graph,
+            &Templates::load(Some(&templates_path))?,
+            &output,
+        )?;
+        let a = fs::read_to_string(output.join("test/a.html"))?;
+        let b = fs::read_to_string(output.join("test/b.html"))?;
+        let tag = fs::read_to_string(output.join("tags/greek-letters.html"))?;
+        let index = fs::read_to_string(output.join("index.html"))?;
+
+        fs::remove_file(input.join("b.md"))?;
+        sourcing::run(&conn, &source)?;
+        extraction::run(&conn, &Default::default())?;
+        resolution::run(&conn)?;
+        let fourth = write(
+            &conn,
+            &graph::load(&conn)?,
+            &Templates::load(Some(&templates_path))?,
+            &output,
+        )?;
+
+        assert_eq!(
+            first,
+            Report {
+                written: 4,
+                unchanged: 0,
+                removed: 0,
+            }
+        );
+        assert_eq!(
+            second,
+            Report {
+                written: 0,
+                unchanged: 4,
+                removed: 0,
+            }
+        );
+        assert_eq!(
+            third,
+            Report {
+                written: 1,
+                unchanged: 3,
+                removed: 0,
+            }
+        );
+        assert_eq!(
+            fourth,
+            Report {
+                written: 2,
+                unchanged: 1,
+                removed: 1,
+            }
+        );
+        assert!(!output.join("test/b.html").exists());
+        assert!(cache::get_output_checksum(&conn, "test/b.html")?.is_none());
+        assert!(a.contains(
+            "<p>See <a href=\"b.html#top\">b</a>, <a href=\"b.html\">Beta</a> and missing.</p>"
+        ));
+        assert!(a.contains("<li><a href=\"../tags/greek-letters.html\">Greek letters</a></li>"));
+        assert!(b.contains(
+            "<section class=\"backlinks\">\n<h2>Backlinks</h2>\n<ul>\n<li><a href=\"a.html\">Alpha</a></li>\n</ul>\n</section>"
+        ));
+        assert!(tag.contains("#Greek letters"));
+        assert!(index.contains(
+            "<li><a href=\"test/a.html\">Alpha</a></li>\n<li><a href=\"test/b.html\">Beta</a></li>"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_site_with_special_names() -> Result<()> {
+        let dir = TempDir::new("html-names")?;
+        let input = dir.join("input");
+        let output = dir.join("output");
+        fs::create_dir_all(&input)?;
+        fs::write(input.join("a b#c.md"), "# Odd\nSee [d](d.md).\n")?;
+        fs::write(input.join("d.md"), "# Plain\n")?;
+
+        let conn = cache::connect(":memory:")?;
+        cache::bootstrap(&conn)?;
+        let source = Source::new("test".parse()?, &input);
+
+        sourcing::run(&conn, &source)?;
+        extraction::run(&conn, &Default::default())?;
+        resolution::run(&conn)?;
+        write(
+            &conn,
+            &graph::load(&conn)?,
+            &Templates::load(None)?,
+            &output,
+        )?;
+        let d = fs::read_to_string(output.join("test/d.html"))?;
+        let index = fs::read_to_string(output.join("index.html"))?;
+
+        assert!(output.join("test/a b#c.html").exists());
+        assert!(d.contains("<li><a href=\"a%20b%23c.html\">Odd</a></li>"));
+        assert!(index.contains("<li><a href=\"test/a%20b%23c.html\">Odd</a></li>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_colliding_urls() -> Result<()> {
+        let dir = TempDir::new("html-collisions")?;
+        let output = dir.join("output");
+        let cases = [
+            (
+                "pages",
+                vec![("a.md", "title: A"), ("a.markdown", "title: A")],
+            ),
+            (
+                "tags",
+                vec![("a.md", "tags: [\"C++\"]"), ("b.md", "tags: [\"C--\"]")],
+            ),
+            ("sources", vec![("c--.md", "tags: [\"C++\"]")]),
+        ];
+
+        for (name, files) in cases.iter() {
+            let input = dir.join(name);
+            fs::create_dir_all(&input)?;
+
+            for (path, metadata) in files {
+                fs::write(input.join(path), format!("---\n{}\n---\n", metadata))?;
+            }
+
+            let id = if *name == "sources" { "tags" } else { name };
+            let source = Source::new(id.parse()?, &input);
+            let conn = cache::connect(":memory:")?;
+            cache::bootstrap(&conn)?;
+
+            sourcing::run(&conn, &source)?;
+            extraction::run(&conn, &Default::default())?;
+            resolution::run(&conn)?;
+            let result = write(
+                &conn,
+                &graph::load(&conn)?,
+                &Templates::load(None)?,
+                &output,
+            );
+
+            assert!(result
+                .unwrap_err()
+                .to_string()
+                .contains("are both rendered to"));
+        }
+
+        Ok(())
+    }
+}
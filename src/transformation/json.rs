@@ -0,0 +1,143 @@
+//! This module is concerned with rendering the graph as a JSON document.
+//!
+//! The document is meant to be consumed by visualisation libraries such as d3 or cytoscape and
+//! its shape is stable across releases. Object keys are sorted.
+//!
+//! ```json
+//! {
+//!   "edges": [
+//!     {
+//!       "label": "ipsum",
+//!       "origin": "unnamed:a.md",
+//!       "target": "unnamed:b.md",
+//!       "type": "inline"
+//!     }
+//!   ],
+//!   "nodes": [
+//!     {
+//!       "checksum": "1e20…",
+//!       "id": "unnamed:a.md",
+//!       "metadata": { "tags": ["lorem"] },
+//!       "path": "a.md",
+//!       "source": "unnamed",
+//!       "title": "Lorem"
+//!     },
+//!     {
+//!       "checksum": "1e20…",
+//!       "id": "unnamed:b.md",
+//!       "metadata": {},
+//!       "path": "b.md",
+//!       "source": "unnamed",
+//!       "title": "Ipsum"
+//!     }
+//!   ],
+//!   "version": 1
+//! }
+//! ```
+//!
+//! * `edges[].origin` and `edges[].target` are node ids.
+//! * `edges[].label` is the text of the connection, e.g. the link text.
+//! * `edges[].type` is one of `inline`, `reference`, `autolink` or `wikilink`.
+//! * `nodes[].id` is the source id and the path joined by a colon, e.g. `unnamed:a.md`.
+//! * `nodes[].checksum` is the multihash of the content the node was extracted from.
+//! * `nodes[].metadata` is the front matter, an empty object when there is none.
+
+use crate::graph::Graph;
+use serde_json::{json, Map, Value};
+
+/// The version of the document shape. It changes only when the shape changes in an incompatible
+/// way.
+pub const VERSION: u32 = 1;
+
+/// Renders the graph as a pretty printed JSON document.
+pub fn render(graph: &Graph) -> String {
+    let nodes: Vec<Value> = graph
+        .nodes
+        .iter()
+        .map(|node| {
+            let metadata: Map<String, Value> = node
+                .metadata
+                .iter()
+                .map(|(k, v)| (k.clone(), v.into()))
+                .collect();
+
+            json!({
+                "id": node.id,
+                "source": node.source_id,
+                "path": node.path,
+                "title": node.title,
+                "metadata": metadata,
+                "checksum": node.content_id,
+            })
+        })
+        .collect();
+    let edges: Vec<Value> = graph
+        .edges
+        .iter()
+        .map(|edge| {
+            json!({
+                "origin": edge.origin_id,
+                "target": edge.target_id,
+                "type": edge.connection_type.as_str(),
+                "label": edge.title,
+            })
+        })
+        .collect();
+    let document = json!({
+        "version": VERSION,
+        "nodes": nodes,
+        "edges": edges,
+    });
+
+    format!("{:#}\n", document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionType;
+    use crate::front_matter::Value as MetadataValue;
+    use crate::graph::tests::node;
+    use crate::graph::Edge;
+
+    #[test]
+    fn render_json() -> Result<(), serde_json::Error> {
+        let graph = Graph {
+            nodes: vec![node("a.md", "unnamed", "Lorem").with(
+                "tags",
+                MetadataValue::Array(vec![MetadataValue::String("lorem".to_string())]),
+            )],
+            edges: vec![Edge {
+                origin_id: "a.md".to_string(),
+                target_id: "a.md".to_string(),
+                connection_type: ConnectionType::Inline,
+                title: "ipsum".to_string(),
+                span: 0..0,
+            }],
+        };
+        let actual: Value = serde_json::from_str(&render(&graph))?;
+
+        assert_eq!(
+            actual,
+            json!({
+                "version": 1,
+                "nodes": [{
+                    "id": "a.md",
+                    "source": "unnamed",
+                    "path": "a.md",
+                    "title": "Lorem",
+                    "metadata": { "tags": ["lorem"] },
+                    "checksum": "1e20",
+                }],
+                "edges": [{
+                    "origin": "a.md",
+                    "target": "a.md",
+                    "type": "inline",
+                    "label": "ipsum",
+                }],
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+//! This module is concerned with the transformation phase.
+//!
+//! Transformation renders the graph stored in the cache into one of the supported output formats,
+//! either a single file or, for HTML, a directory.
+
+pub mod dot;
+pub mod gexf;
+pub mod graphml;
+pub mod html;
+pub mod json;
+pub mod templates;
+
+use crate::context::Result as ContextResult;
+use crate::front_matter::Value;
+use crate::graph::{self, Graph};
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use templates::Templates;
+
+/// The output formats of the transformation phase.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// A Graphviz DOT file.
+    Dot,
+    /// A JSON document of nodes and edges.
+    Json,
+    /// A GraphML document, e.g. for yEd.
+    Graphml,
+    /// A GEXF document, e.g. for Gephi.
+    Gexf,
+    /// A static HTML site.
+    Html,
+}
+
+impl Format {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Dot => "dot",
+            Format::Json => "json",
+            Format::Graphml => "graphml",
+            Format::Gexf => "gexf",
+            Format::Html => "html",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Format {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Format::Dot),
+            "json" => Ok(Format::Json),
+            "graphml" => Ok(Format::Graphml),
+            "gexf" => Ok(Format::Gexf),
+            "html" => Ok(Format::Html),
+            _ => Err(FormatError(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FormatError(String);
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown output format `{}`", self.0)
+    }
+}
+
+impl Error for FormatError {}
+
+/// The options shared by every output format.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Options {
+    /// Whether to group nodes by source, where the format supports it.
+    pub cluster: bool,
+    /// The directory to load HTML templates from, if any.
+    pub templates_path: Option<PathBuf>,
+}
+
+/// A summary of the files written by the transformation phase.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Report {
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} files written, {} unchanged, {} removed.",
+            self.written, self.unchanged, self.removed
+        )
+    }
+}
+
+/// Renders the graph stored in the cache in the given format into the output path.
+pub fn run(
+    conn: &Connection,
+    format: Format,
+    options: &Options,
+    output_path: &Path,
+) -> ContextResult<Report> {
+    let graph = graph::load(conn)?;
+    let document = match format {
+        Format::Dot => dot::render(&graph, options),
+        Format::Json => json::render(&graph),
+        Format::Graphml => graphml::render(&graph),
+        Format::Gexf => gexf::render(&graph),
+        Format::Html => {
+            let templates = Templates::load(options.templates_path.as_deref())?;
+
+            return html::write(conn, &graph, &templates, output_path);
+        }
+    };
+
+    fs::write(output_path, document)?;
+
+    Ok(Report {
+        written: 1,
+        ..Default::default()
+    })
+}
+
+/// The type of a front matter attribute, as declared by typed graph formats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttributeType {
+    Boolean,
+    Integer,
+    Float,
+    /// Strings, datetimes, and arrays and tables encoded as JSON.
+    String,
+}
+
+impl AttributeType {
+    /// The type name as used by both GraphML and GEXF.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributeType::Boolean => "boolean",
+            AttributeType::Integer => "long",
+            AttributeType::Float => "double",
+            AttributeType::String => "string",
+        }
+    }
+
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => None,
+            Value::Boolean(_) => Some(AttributeType::Boolean),
+            Value::Integer(_) => Some(AttributeType::Integer),
+            Value::Float(_) => Some(AttributeType::Float),
+            _ => Some(AttributeType::String),
+        }
+    }
+
+    /// The narrowest type both types fit in.
+    fn merge(self, other: Self) -> Self {
+        use AttributeType::*;
+
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Integer, Float) | (Float, Integer) => Float,
+            _ => String,
+        }
+    }
+}
+
+/// Infers the type of every front matter key found in the nodes of the graph, sorted by key.
+///
+/// A key holding values of different types across nodes falls back to a string, except for a mix
+/// of integers and floats which is a float.
+pub fn attributes(graph: &Graph) -> Vec<(String, AttributeType)> {
+    let mut types: BTreeMap<&str, AttributeType> = BTreeMap::new();
+
+    for node in &graph.nodes {
+        for (key, value) in &node.metadata {
+            if let Some(kind) = AttributeType::of(value) {
+                let merged = types.get(key.as_str()).map_or(kind, |t| t.merge(kind));
+                types.insert(key, merged);
+            }
+        }
+    }
+
+    types
+        .into_iter()
+        .map(|(key, kind)| (key.to_string(), kind))
+        .collect()
+}
+
+/// Formats a front matter value as an attribute value. Nulls have no value.
+pub fn attribute_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) | Value::Datetime(s) => Some(s.clone()),
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Float(f) => Some(f.to_string()),
+        Value::Boolean(b) => Some(b.to_string()),
+        Value::Array(_) | Value::Table(_) => Some(serde_json::Value::from(value).to_string()),
+    }
+}
+
+/// Escapes text for use in XML content and attribute values.
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::tests::node;
+
+    #[test]
+    fn infer_attributes() {
+        let graph = Graph {
+            nodes: vec![
+                node("a.md", "x", "A")
+                    .with("draft", Value::Boolean(true))
+                    .with("rank", Value::Integer(1))
+                    .with("id", Value::Integer(1))
+                    .with("tags", Value::Null),
+                node("b.md", "x", "B")
+                    .with("rank", Value::Float(0.5))
+                    .with("id", Value::String("b".to_string())),
+            ],
+            edges: vec![],
+        };
+
+        assert_eq!(
+            attributes(&graph),
+            vec![
+                ("draft".to_string(), AttributeType::Boolean),
+                ("id".to_string(), AttributeType::String),
+                ("rank".to_string(), AttributeType::Float),
+            ]
+        );
+    }
+}
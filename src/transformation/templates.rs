@@ -0,0 +1,116 @@
+//! This module is concerned with the templates of the HTML output.
+//!
+//! A templates directory may provide any of `layout.html`, `node.html`, `index.html` and
+//! `tag.html`. Missing templates fall back to the built-in ones. Templates use the Jinja syntax
+//! and are expected to extend `layout.html`.
+
+use super::escape_xml;
+use crate::checksum::Checksum;
+use crate::context::Result;
+use minijinja::{escape_formatter, AutoEscape, Environment, Value};
+use std::fs;
+use std::path::Path;
+
+/// The layout every page template extends.
+pub const LAYOUT: &str = "layout.html";
+
+/// The template of the page of a node.
+///
+/// Exposes `title`, `root`, `node` (`id`, `source`, `path`, `title`, `url`, `metadata`,
+/// `checksum`, `history`), `body`, `links`, `backlinks` and `tags`. The `history` (`created`,
+/// `updated`, `authors`) is only set for entries of Git sources.
+pub const NODE: &str = "node.html";
+
+/// The template of the index page. Exposes `title`, `root`, `nodes` and `tags`.
+pub const INDEX: &str = "index.html";
+
+/// The template of the page of a tag. Exposes `title`, `root`, `tag` and `nodes`.
+pub const TAG: &str = "tag.html";
+
+/// The set of known templates along with their built-in source.
+const DEFAULTS: [(&str, &str); 4] = [
+    (LAYOUT, include_str!("../templates/layout.html")),
+    (NODE, include_str!("../templates/node.html")),
+    (INDEX, include_str!("../templates/index.html")),
+    (TAG, include_str!("../templates/tag.html")),
+];
+
+/// The templates used to render the HTML output.
+#[derive(Debug)]
+pub struct Templates {
+    env: Environment<'static>,
+    checksums: Vec<(&'static str, Checksum)>,
+}
+
+impl Templates {
+    /// Loads the templates from the given directory, if any, falling back to the built-in ones.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut env = Environment::new();
+        let mut checksums = Vec::new();
+
+        // Escapes strings like the rest of the HTML output so URLs keep their slashes.
+        env.set_formatter(|out, state, value| match value.as_str() {
+            Some(s) if state.auto_escape() == AutoEscape::Html && !value.is_safe() => {
+                out.write_str(&escape_xml(s))?;
+
+                Ok(())
+            }
+            _ => escape_formatter(out, state, value),
+        });
+
+        for (name, default) in &DEFAULTS {
+            let source = match path.map(|path| path.join(name)) {
+                Some(file) if file.exists() => fs::read_to_string(file)?,
+                _ => default.to_string(),
+            };
+
+            checksums.push((*name, Checksum::new(source.as_bytes())));
+            env.add_template_owned(*name, source)
+                .map_err(|err| format!("{}: {}", name, err))?;
+        }
+
+        Ok(Templates { env, checksums })
+    }
+
+    /// The checksum of the source of every template, keyed by template name.
+    pub fn checksums(&self) -> &[(&'static str, Checksum)] {
+        &self.checksums
+    }
+
+    /// Renders the given template with the given data.
+    pub fn render(&self, name: &str, data: &serde_json::Value) -> Result<String> {
+        let template = self.env.get_template(name)?;
+        let html = template
+            .render(Value::from_serialize(data))
+            .map_err(|err| format!("{}: {}", name, err))?;
+
+        Ok(html)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::tests::TempDir;
+    use serde_json::json;
+
+    #[test]
+    fn override_templates() -> Result<()> {
+        let dir = TempDir::new("templates")?;
+        fs::write(
+            dir.join(TAG),
+            "{% extends \"layout.html\" %}{% block content %}#{{ tag }}{% endblock %}",
+        )?;
+
+        let defaults = Templates::load(None)?;
+        let templates = Templates::load(Some(&dir))?;
+        let html = templates.render(TAG, &json!({ "title": "<a>", "tag": "b&c" }))?;
+
+        assert!(html.contains("<title>&lt;a&gt;</title>"));
+        assert!(html.contains("#b&amp;c"));
+        assert_eq!(defaults.checksums()[0], templates.checksums()[0]);
+        assert_ne!(defaults.checksums()[3], templates.checksums()[3]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+//! This module is concerned with watching the sources for changes.
+//!
+//! Filesystem events under every source route are debounced and every burst of them triggers a
+//! single rebuild. Rebuilds rely on the pipeline being incremental: only entries whose stamp
+//! changed are read again and only those are extracted again.
+
+use crate::context::Result;
+use crate::source::Source;
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+/// The time to wait for filesystem events to settle before rebuilding.
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The suffixes of the files SQLite writes next to a database.
+const SQLITE_SUFFIXES: [&str; 3] = ["-wal", "-shm", "-journal"];
+
+/// Watches the routes of the given sources and calls `rebuild` after every burst of changes,
+/// until the watcher is gone. Errors reported by the watcher are logged and watching goes on.
+///
+/// Changes to the given ignored paths, typically the cache and the outputs, are left out so
+/// writing them doesn't trigger another rebuild.
+pub fn run<F>(sources: &[Source], ignored: &[PathBuf], mut rebuild: F) -> Result<()>
+where
+    F: FnMut(),
+{
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, DEBOUNCE)?;
+    let ignored: Vec<PathBuf> = ignored.iter().map(|path| absolute(path)).collect();
+
+    for source in sources {
+        let route = source.route();
+        let mode = if route.is_dir() {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+
+        watcher
+            .watch(route, mode)
+            .map_err(|err| format!("{}: {}", route.display(), err))?;
+    }
+
+    loop {
+        let mut changed = is_relevant(&rx.recv()?, &ignored);
+
+        while let Ok(event) = rx.try_recv() {
+            changed |= is_relevant(&event, &ignored);
+        }
+
+        if changed {
+            rebuild();
+        }
+    }
+}
+
+/// Whether an event should trigger a rebuild, logging errors.
+fn is_relevant(event: &DebouncedEvent, ignored: &[PathBuf]) -> bool {
+    let paths = match event {
+        DebouncedEvent::Create(path)
+        | DebouncedEvent::Write(path)
+        | DebouncedEvent::Remove(path) => {
+            vec![path]
+        }
+        DebouncedEvent::Rename(from, to) => vec![from, to],
+        DebouncedEvent::Rescan => return true,
+        DebouncedEvent::Error(err, path) => {
+            match path {
+                Some(path) => eprintln!("{}: {}", path.display(), err),
+                None => eprintln!("{}", err),
+            }
+
+            return false;
+        }
+        DebouncedEvent::NoticeWrite(_)
+        | DebouncedEvent::NoticeRemove(_)
+        | DebouncedEvent::Chmod(_) => return false,
+    };
+
+    paths
+        .iter()
+        .any(|path| !ignored.iter().any(|ignored| is_under(path, ignored)))
+}
+
+/// Whether a path is the given path or is inside it, including SQLite companion files such as
+/// `onelo.db-wal`.
+fn is_under(path: &Path, ignored: &Path) -> bool {
+    if path.starts_with(ignored) {
+        return true;
+    }
+
+    match (path.to_str(), ignored.to_str()) {
+        (Some(path), Some(ignored)) => path
+            .strip_prefix(ignored)
+            .is_some_and(|suffix| SQLITE_SUFFIXES.contains(&suffix)),
+        _ => false,
+    }
+}
+
+/// Makes a path absolute resolving symbolic links as far as it exists, given that events are
+/// reported with canonical paths.
+fn absolute(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => absolute(parent).join(name),
+        _ => env::current_dir().unwrap_or_default().join(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relevant_events() {
+        let ignored = vec![
+            PathBuf::from("/notes/onelo.db"),
+            PathBuf::from("/notes/site"),
+        ];
+        let event = |path: &str| DebouncedEvent::Write(PathBuf::from(path));
+
+        assert!(is_relevant(&event("/notes/a.md"), &ignored));
+        assert!(is_relevant(&event("/notes/site.md"), &ignored));
+        assert!(is_relevant(&event("/notes/site-plan.md"), &ignored));
+        assert!(is_relevant(&event("/notes/site-old/x.md"), &ignored));
+        assert!(is_relevant(&event("/notes/onelo.db-notes.md"), &ignored));
+        assert!(!is_relevant(&event("/notes/onelo.db"), &ignored));
+        assert!(!is_relevant(&event("/notes/onelo.db-wal"), &ignored));
+        assert!(!is_relevant(&event("/notes/onelo.db-journal"), &ignored));
+        assert!(!is_relevant(&event("/notes/site/index.html"), &ignored));
+        assert!(!is_relevant(
+            &DebouncedEvent::Chmod(PathBuf::from("/notes/a.md")),
+            &ignored
+        ));
+        assert!(!is_relevant(
+            &DebouncedEvent::Error(notify::Error::Generic("gone".into()), None),
+            &ignored
+        ));
+        assert!(is_relevant(
+            &DebouncedEvent::Rename(
+                PathBuf::from("/notes/site/a.md"),
+                PathBuf::from("/notes/a.md")
+            ),
+            &ignored
+        ));
+    }
+}